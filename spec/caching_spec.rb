@@ -0,0 +1,42 @@
+require 'net/http'
+
+def get_raw(path, headers = {})
+  uri = URI("http://localhost:3000#{path}")
+  Net::HTTP.start(uri.host, uri.port) do |http|
+    http.get(uri.request_uri, headers)
+  end
+end
+
+describe "caching" do
+  before(:all) do
+    @response = get_raw("/competitors/2007HABE01")
+  end
+
+  it "sets validators and Cache-Control" do
+    expect(@response["ETag"]).to match(/\A"[0-9a-f]+-[0-9a-f]+"\z/)
+    expect(@response["Last-Modified"]).to end_with "GMT"
+    expect(@response["Cache-Control"]).to start_with "public, max-age="
+  end
+
+  it "returns 304 for a matching If-None-Match" do
+    res = get_raw("/competitors/2007HABE01", "If-None-Match" => @response["ETag"])
+    expect(res.code).to eq "304"
+    expect(res.body).to be_nil
+  end
+
+  it "returns 304 when not modified since" do
+    res = get_raw("/competitors/2007HABE01", "If-Modified-Since" => @response["Last-Modified"])
+    expect(res.code).to eq "304"
+  end
+
+  it "uses different ETags per representation" do
+    csv = get_raw("/competitors?q=Habermaas", "Accept" => "text/csv")
+    json = get_raw("/competitors?q=Habermaas")
+    expect(csv["ETag"]).not_to eq json["ETag"]
+  end
+
+  it "returns the full response for a stale ETag" do
+    res = get_raw("/competitors/2007HABE01", "If-None-Match" => "\"stale\"")
+    expect(res.code).to eq "200"
+  end
+end
@@ -55,4 +55,33 @@ describe "competitors" do
       expect(competitors.first["country"]).to eq "France"
     end
   end
+
+  describe "batch lookup" do
+    it "returns the requested competitors and the unknown ids" do
+      response = get("/competitors?ids=2007HABE01&ids=1900NONE01&ids=2003POCH01")
+      expect(response["competitors"].map { |c| c["id"] }).to eq ["2007HABE01", "2003POCH01"]
+      expect(response["not_found"]).to eq ["1900NONE01"]
+    end
+
+    it "accepts the ids as POST body" do
+      uri = URI("http://localhost:3000/competitors")
+      res = Net::HTTP.post(uri, { ids: ["2007HABE01", "1900NONE01"] }.to_json, "Content-Type" => "application/json")
+      response = JSON.parse(res.body)
+      expect(response["competitors"].first["name"]).to eq "Tim Habermaas"
+      expect(response["not_found"]).to eq ["1900NONE01"]
+    end
+
+    it "rejects ids which are not strings" do
+      uri = URI("http://localhost:3000/competitors")
+      res = Net::HTTP.post(uri, { ids: ["2007HABE01", 42] }.to_json, "Content-Type" => "application/json")
+      expect(res.code).to eq "400"
+      expect(JSON.parse(res.body)["error"]["details"]).to eq "ids"
+    end
+
+    it "rejects bodies over the size limit" do
+      uri = URI("http://localhost:3000/competitors")
+      res = Net::HTTP.post(uri, { ids: ["2007HABE01"] * 6000 }.to_json, "Content-Type" => "application/json")
+      expect(res.code).to eq "413"
+    end
+  end
 end
@@ -0,0 +1,38 @@
+require 'net/http'
+require 'json'
+require 'zlib'
+require 'stringio'
+
+def get_raw(path, headers = {})
+  uri = URI("http://localhost:3000#{path}")
+  Net::HTTP.start(uri.host, uri.port) do |http|
+    http.get(uri.request_uri, headers)
+  end
+end
+
+describe "compression" do
+  it "gzips full rankings" do
+    res = get_raw("/records/333mbf/single", "Accept-Encoding" => "gzip")
+    expect(res["Content-Encoding"]).to eq "gzip"
+    expect(res["Vary"]).to include "Accept-Encoding"
+    records = JSON.parse(Zlib::GzipReader.new(StringIO.new(res.body)).read)
+    expect(records.size).to eq 926
+  end
+
+  it "prefers brotli when accepted" do
+    res = get_raw("/records/333mbf/single", "Accept-Encoding" => "gzip, br")
+    expect(res["Content-Encoding"]).to eq "br"
+  end
+
+  it "leaves small responses uncompressed" do
+    res = get_raw("/competitors/2007HABE01", "Accept-Encoding" => "gzip")
+    expect(res["Content-Encoding"]).to be_nil
+    expect(JSON.parse(res.body)["competitor"]["id"]).to eq "2007HABE01"
+  end
+
+  it "does not compress without Accept-Encoding" do
+    res = get_raw("/records/333mbf/single", "Accept-Encoding" => "identity")
+    expect(res["Content-Encoding"]).to be_nil
+    expect(JSON.parse(res.body).size).to eq 926
+  end
+end
@@ -0,0 +1,36 @@
+require 'net/http'
+
+# Expects the server to be started with CORS_ALLOWED_ORIGINS=https://example.org
+
+def request_with(method, path, headers = {})
+  uri = URI("http://localhost:3000#{path}")
+  Net::HTTP.start(uri.host, uri.port) do |http|
+    http.send_request(method, uri.request_uri, nil, headers)
+  end
+end
+
+describe "cors" do
+  it "allows listed origins and exposes pagination headers" do
+    res = request_with("GET", "/competitors/2007HABE01/competitions", "Origin" => "https://example.org")
+    expect(res["Access-Control-Allow-Origin"]).to eq "https://example.org"
+    expect(res["Access-Control-Expose-Headers"]).to include "X-Total-Count"
+    expect(res["Access-Control-Expose-Headers"]).to include "ETag"
+  end
+
+  it "ignores other origins" do
+    res = request_with("GET", "/competitors/2007HABE01", "Origin" => "https://evil.example.org")
+    expect(res["Access-Control-Allow-Origin"]).to be_nil
+  end
+
+  it "answers preflight requests for registered routes" do
+    res = request_with("OPTIONS", "/graphql", "Origin" => "https://example.org", "Access-Control-Request-Method" => "POST")
+    expect(res.code).to eq "204"
+    expect(res["Access-Control-Allow-Origin"]).to eq "https://example.org"
+    expect(res["Access-Control-Allow-Methods"]).to eq "GET, POST, OPTIONS"
+  end
+
+  it "answers preflight requests for unknown routes with 404" do
+    res = request_with("OPTIONS", "/does-not-exist", "Origin" => "https://example.org")
+    expect(res.code).to eq "404"
+  end
+end
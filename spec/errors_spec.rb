@@ -0,0 +1,73 @@
+require 'net/http'
+require 'json'
+
+def request(method, path)
+  uri = URI("http://localhost:3000#{path}")
+  Net::HTTP.start(uri.host, uri.port) do |http|
+    http.send_request(method, uri.request_uri)
+  end
+end
+
+describe "errors" do
+  def error_of(res)
+    JSON.parse(res.body)["error"]
+  end
+
+  it "returns 404 with an error envelope for unknown competitors" do
+    res = request("GET", "/competitors/1900NONE01")
+    expect(res.code).to eq "404"
+    expect(error_of(res)["code"]).to eq "not_found"
+    expect(error_of(res)["message"]).to eq "competitor not found"
+  end
+
+  it "returns 404 for the medals of an unknown country" do
+    res = request("GET", "/medals?country=Atlantis")
+    expect(res.code).to eq "404"
+    expect(error_of(res)["message"]).to eq "country not found"
+  end
+
+  it "returns 404 for unknown routes" do
+    res = request("GET", "/does-not-exist")
+    expect(res.code).to eq "404"
+    expect(error_of(res)["code"]).to eq "not_found"
+  end
+
+  it "returns 405 for unsupported methods" do
+    res = request("DELETE", "/competitors/2007HABE01")
+    expect(res.code).to eq "405"
+    expect(res["Allow"]).to eq "GET"
+    expect(error_of(res)["code"]).to eq "method_not_allowed"
+  end
+
+  it "returns 400 instead of failing without a search query" do
+    res = request("GET", "/competitors")
+    expect(res.code).to eq "400"
+    expect(error_of(res)["code"]).to eq "missing_parameter"
+    expect(error_of(res)["details"]).to eq "q"
+  end
+
+  it "returns 400 instead of failing without ids" do
+    res = request("GET", "/records/333/")
+    expect(res.code).to eq "400"
+    expect(error_of(res)["details"]).to eq "ids"
+  end
+
+  it "returns 404 for the record history of an unknown event" do
+    res = request("GET", "/records/nope/history")
+    expect(res.code).to eq "404"
+    expect(error_of(res)["message"]).to eq "event not found"
+  end
+
+  it "returns 400 for histograms with too many buckets" do
+    ["1000000000", "4294967296", "0"].each do |buckets|
+      res = request("GET", "/stats/333/single?buckets=#{buckets}")
+      expect(res.code).to eq "400"
+      expect(error_of(res)["details"]).to eq 100
+    end
+  end
+
+  it "returns 400 for an unknown gender" do
+    res = request("GET", "/stats/333/single?gender=x")
+    expect(res.code).to eq "400"
+  end
+end
@@ -0,0 +1,60 @@
+require 'net/http'
+require 'json'
+
+def get_raw(path, headers = {})
+  uri = URI("http://localhost:3000#{path}")
+  Net::HTTP.start(uri.host, uri.port) do |http|
+    http.get(uri.request_uri, headers)
+  end
+end
+
+describe "formats" do
+  it "returns CSV records for Accept: text/csv" do
+    res = get_raw("/records/333mbf/single", "Accept" => "text/csv")
+    lines = res.body.split("\r\n")
+    expect(res["Content-Type"]).to start_with "text/csv"
+    expect(lines.first).to eq "rank,time,competitor_id,name,country,gender"
+    expect(lines.size).to eq 927
+    expect(lines[1]).to start_with "1,580325400,"
+  end
+
+  it "returns TSV records for ?format=tsv" do
+    res = get_raw("/records/333?ids=2007HABE01&ids=2003POCH01&format=tsv")
+    expect(res["Content-Type"]).to start_with "text/tab-separated-values"
+    lines = res.body.split("\r\n")
+    expect(lines.size).to eq 3
+    expect(lines[0]).to eq "competitor_id\tsingle\taverage"
+    expect(lines[1]).to eq "2003POCH01\t956\t1273"
+    expect(lines[2]).to start_with "2007HABE01\t"
+  end
+
+  it "prefers the media type with the highest q-value" do
+    res = get_raw("/competitors?q=Habermaas", "Accept" => "text/csv;q=0.1, application/json")
+    expect(res["Content-Type"]).to start_with "application/json"
+  end
+
+  it "returns CSV competitors" do
+    res = get_raw("/competitors?q=Habermaas&format=csv")
+    expect(res.body.split("\r\n").first).to eq "id,name,country,gender,competition_count"
+  end
+
+  it "rejects unknown formats" do
+    res = get_raw("/competitors?q=Habermaas&format=xml")
+    expect(res.code).to eq "400"
+  end
+
+  it "keeps JSON as default" do
+    res = get_raw("/competitors?q=Habermaas")
+    expect(res["Content-Type"]).to start_with "application/json"
+  end
+end
+
+describe "ndjson" do
+  it "streams one ranking per line" do
+    res = get_raw("/records/333mbf/single", "Accept" => "application/x-ndjson")
+    lines = res.body.split("\n")
+    expect(res["Content-Type"]).to start_with "application/x-ndjson"
+    expect(lines.size).to eq 926
+    expect(JSON.parse(lines.first)["time"]).to eq 580325400
+  end
+end
@@ -0,0 +1,52 @@
+require 'net/http'
+require 'json'
+
+describe "graphql" do
+  def graphql_get(query)
+    uri = URI("http://localhost:3000/graphql")
+    uri.query = URI.encode_www_form(query: query)
+    Net::HTTP.get_response(uri)
+  end
+
+  def graphql_post(body)
+    uri = URI("http://localhost:3000/graphql")
+    Net::HTTP.start(uri.host, uri.port) do |http|
+      http.post(uri.request_uri, body, "Content-Type" => "application/json")
+    end
+  end
+
+  it "resolves a competitor with records" do
+    res = graphql_get('{ competitor(id: "2007HABE01") { name records(first: 1) { event { id } single } } }')
+    expect(res.code).to eq "200"
+    competitor = JSON.parse(res.body)["data"]["competitor"]
+    expect(competitor["name"]).to eq "Tim Habermaas"
+    expect(competitor["records"].size).to eq 1
+    expect(competitor["records"].first["single"]).to be_a Integer
+  end
+
+  it "takes the query from a JSON body" do
+    res = graphql_post({ query: '{ rankings(event: "333mbf", first: 3) { rank time } }' }.to_json)
+    expect(res.code).to eq "200"
+    rankings = JSON.parse(res.body)["data"]["rankings"]
+    expect(rankings.size).to eq 3
+    expect(rankings.first["time"]).to eq 580325400
+  end
+
+  it "rejects queries nested too deeply" do
+    res = graphql_get('{ competitor(id: "2007HABE01") { competitions { competitors { competitions { competitors { competitions { id } } } } } } }')
+    expect(res.code).to eq "400"
+    expect(JSON.parse(res.body)["errors"].first["message"]).to include "deeper than 6"
+  end
+
+  it "rejects too complex queries" do
+    res = graphql_get('{ rankings(event: "333", first: 1000) { competitor { competitions(first: 1000) { id } } } }')
+    expect(res.code).to eq "400"
+    expect(JSON.parse(res.body)["errors"].first["message"]).to include "complexity"
+  end
+
+  it "returns 413 for bodies over the limit" do
+    res = graphql_post("#" * (64 * 1024 + 1))
+    expect(res.code).to eq "413"
+    expect(JSON.parse(res.body)["error"]["code"]).to eq "payload_too_large"
+  end
+end
@@ -0,0 +1,25 @@
+require 'net/http'
+
+def get_with(path, headers = {})
+  uri = URI("http://localhost:3000#{path}")
+  Net::HTTP.start(uri.host, uri.port) do |http|
+    http.get(uri.request_uri, headers)
+  end
+end
+
+describe "request ids" do
+  it "generates a request id" do
+    first = get_with("/events")["X-Request-Id"]
+    second = get_with("/events")["X-Request-Id"]
+    expect(first).not_to be_empty
+    expect(first).not_to eq second
+  end
+
+  it "keeps the request id of the client" do
+    expect(get_with("/events", "X-Request-Id" => "abc-123")["X-Request-Id"]).to eq "abc-123"
+  end
+
+  it "sets the request id on errors" do
+    expect(get_with("/does-not-exist")["X-Request-Id"]).not_to be_nil
+  end
+end
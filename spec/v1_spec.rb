@@ -0,0 +1,60 @@
+require 'net/http'
+require 'json'
+
+def get_v1(path)
+  uri = URI("http://localhost:3000/v1#{path}")
+  Net::HTTP.start(uri.host, uri.port) do |http|
+    http.get(uri.request_uri)
+  end
+end
+
+describe "v1" do
+  it "returns a competitor as a bare object" do
+    competitor = JSON.parse(get_v1("/competitors/2007HABE01").body)
+    expect(competitor["id"]).to eq "2007HABE01"
+    expect(competitor["name"]).to eq "Tim Habermaas"
+    expect(competitor["competition_count"]).to eq 36
+  end
+
+  it "returns an unknown gender as null" do
+    competitor = JSON.parse(get_v1("/competitors/2014RODR25").body)
+    expect(competitor.fetch("gender")).to be_nil
+  end
+
+  it "returns records of a competitor as a list" do
+    records = JSON.parse(get_v1("/competitors/2007HABE01/records").body)["records"]
+    record = records.find { |r| r["puzzle_id"] == "333" }
+    expect(record["single"]).to be_a Integer
+    expect(record.keys).to eq ["puzzle_id", "single", "average"]
+  end
+
+  it "paginates rankings" do
+    res = get_v1("/records/333mbf/single?per_page=10&page=2")
+    rankings = JSON.parse(res.body)["rankings"]
+    expect(res["X-Total-Count"]).to eq "926"
+    expect(rankings.size).to eq 10
+    expect(rankings.first["rank"]).to be >= 11
+  end
+
+  it "returns selected records wrapped" do
+    records = JSON.parse(get_v1("/records/333/?ids=2007HABE01&ids=2003POCH01").body)["records"]
+    expect(records.first["competitor_id"]).to eq "2003POCH01"
+    expect(records.first["single"]).to eq 956
+    expect(records.first["average"]).to eq 1273
+  end
+
+  it "returns events wrapped" do
+    events = JSON.parse(get_v1("/events").body)["events"]
+    expect(events.first.keys).to eq ["id", "name"]
+  end
+
+  it "wraps collections which are bare arrays in the legacy routes" do
+    expect(JSON.parse(get_v1("/medals").body)["medals"]).to be_a Array
+    expect(JSON.parse(get_v1("/kinch?events=333").body)["ranks"]).to be_a Array
+    expect(JSON.parse(get_v1("/competitors/2007HABE01/progression/333").body)["progression"]).to be_a Array
+  end
+
+  it "serves the other routes as well" do
+    expect(get_v1("/countries").code).to eq "200"
+  end
+end
@@ -0,0 +1,155 @@
+use std::hash::{Hash, Hasher, SipHasher};
+use std::str;
+
+use w::wca_data;
+
+use iron::{Request, Response, IronResult, AfterMiddleware};
+use iron::status;
+use iron::method::Method;
+
+const MONTHS: [&'static str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+const WEEKDAYS: [&'static str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+/// Adds `ETag`, `Last-Modified` and `Cache-Control` to successful GET
+/// responses and turns them into a 304 when the client's copy is still
+/// current. Responses only change when another export is loaded, so the
+/// ETag is the export version combined with the request.
+pub struct ConditionalGetMiddleware {
+    version: String,
+    // Seconds since 1970-01-01
+    last_modified: Option<i64>,
+    max_age: u32,
+}
+
+impl ConditionalGetMiddleware {
+    pub fn new(data: &wca_data::WCA, max_age: u32) -> ConditionalGetMiddleware {
+        ConditionalGetMiddleware {
+            version: data.export_version(),
+            last_modified: data.export_date().map(|days| days * 86400),
+            max_age: max_age,
+        }
+    }
+
+    // The representation depends on path, query and the Accept header (CSV or JSON).
+    fn etag_for(&self, req: &Request) -> String {
+        let mut hasher = SipHasher::new();
+        req.url.path.hash(&mut hasher);
+        req.url.query.hash(&mut hasher);
+        header_value(req, "Accept").hash(&mut hasher);
+        format!("\"{}-{:x}\"", self.version, hasher.finish())
+    }
+
+    // If-None-Match takes precedence, If-Modified-Since is only looked at without it.
+    fn is_fresh(&self, req: &Request, etag: &str) -> bool {
+        match header_value(req, "If-None-Match") {
+            Some(tags) => tags.split(',').any(|tag| {
+                let tag = tag.trim();
+                tag == "*" || tag == etag || tag.trim_left_matches("W/") == etag
+            }),
+            None => match (header_value(req, "If-Modified-Since").and_then(|d| parse_http_date(d.as_slice())), self.last_modified) {
+                (Some(since), Some(last_modified)) => last_modified <= since,
+                _                                  => false,
+            },
+        }
+    }
+}
+
+impl AfterMiddleware for ConditionalGetMiddleware {
+    fn after(&self, req: &mut Request, res: Response) -> IronResult<Response> {
+        let mut response = res;
+        if (req.method != Method::Get && req.method != Method::Head) || response.status != Some(status::Ok) {
+            return Ok(response);
+        }
+
+        let etag = self.etag_for(req);
+        response.headers.set_raw("ETag", vec![etag.clone().into_bytes()]);
+        if let Some(last_modified) = self.last_modified {
+            response.headers.set_raw("Last-Modified", vec![http_date(last_modified).into_bytes()]);
+        }
+        response.headers.set_raw("Cache-Control", vec![format!("public, max-age={}", self.max_age).into_bytes()]);
+        append_vary(&mut response, "Accept");
+
+        if self.is_fresh(req, etag.as_slice()) {
+            response.status = Some(status::NotModified);
+            response.body = None;
+        }
+        Ok(response)
+    }
+}
+
+/// Adds a header name to `Vary`, keeping the ones other middleware added.
+pub fn append_vary(response: &mut Response, name: &str) {
+    let vary = match response.headers.get_raw("Vary").and_then(|v| str::from_utf8(v[0].as_slice()).ok()) {
+        Some(vary) => format!("{}, {}", vary, name),
+        None       => name.to_string(),
+    };
+    response.headers.set_raw("Vary", vec![vary.into_bytes()]);
+}
+
+pub fn header_value(req: &Request, name: &str) -> Option<String> {
+    req.headers.get_raw(name).map(|values| {
+        let values: Vec<&str> = values.iter().filter_map(|v| str::from_utf8(v.as_slice()).ok()).collect();
+        values.connect(",")
+    })
+}
+
+// Inverse of days_from_civil
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = (if z >= 0 { z } else { z - 146096 }) / 146097;
+    let day_of_era = z - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (year_of_era + era * 400 + (if month <= 2 { 1 } else { 0 }), month, day)
+}
+
+/// Formats seconds since 1970-01-01 as IMF-fixdate, e.g. `Sat, 04 Dec 2004 00:00:00 GMT`.
+pub fn http_date(seconds: i64) -> String {
+    let days = seconds / 86400;
+    let secs = seconds % 86400;
+    let (year, month, day) = civil_from_days(days);
+    format!("{}, {:02} {} {} {:02}:{:02}:{:02} GMT", WEEKDAYS[((days + 4) % 7) as usize], day, MONTHS[(month - 1) as usize], year,
+            secs / 3600, (secs / 60) % 60, secs % 60)
+}
+
+/// Parses an IMF-fixdate, the only format clients are required to send.
+pub fn parse_http_date(date: &str) -> Option<i64> {
+    let parts: Vec<&str> = date.split(' ').filter(|p| !p.is_empty()).collect();
+    if parts.len() != 6 || parts[5] != "GMT" {
+        return None;
+    }
+    let day: Option<i64> = parts[1].parse().ok();
+    let month = MONTHS.iter().position(|m| *m == parts[2]).map(|m| m as i64 + 1);
+    let year: Option<i64> = parts[3].parse().ok();
+    let time: Vec<Option<i64>> = parts[4].split(':').map(|t| t.parse().ok()).collect();
+    if time.len() != 3 {
+        return None;
+    }
+    match (year, month, day, time[0], time[1], time[2]) {
+        (Some(year), Some(month), Some(day), Some(h), Some(m), Some(s)) => {
+            Some(wca_data::days_from_civil(year, month, day) * 86400 + h * 3600 + m * 60 + s)
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{http_date, parse_http_date};
+
+    #[test]
+    fn formats_http_dates() {
+        assert_eq!(http_date(0), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(http_date(12756 * 86400), "Sat, 04 Dec 2004 00:00:00 GMT");
+    }
+
+    #[test]
+    fn parses_http_dates() {
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), Some(784111777));
+        assert_eq!(parse_http_date(http_date(12756 * 86400).as_slice()), Some(12756 * 86400));
+        assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), None);
+    }
+}
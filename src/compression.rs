@@ -0,0 +1,146 @@
+use std::ascii::AsciiExt;
+use std::io::Read;
+use std::str;
+
+use flate2;
+use flate2::read::GzEncoder;
+use brotli2::read::BrotliEncoder;
+
+use iron::{Request, Response, IronResult, AfterMiddleware};
+use iron::status;
+use iron::headers;
+
+use cache::{append_vary, header_value};
+
+const BROTLI_QUALITY: u32 = 5;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    fn name(&self) -> &'static str {
+        match *self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip   => "gzip",
+        }
+    }
+
+    // Wraps the body, the encoders compress while hyper reads from them so
+    // large rankings are never held compressed and uncompressed at once.
+    fn encode(&self, body: Box<Read + Send>) -> Box<Read + Send> {
+        match *self {
+            Encoding::Brotli => Box::new(BrotliEncoder::new(body, BROTLI_QUALITY)),
+            Encoding::Gzip   => Box::new(GzEncoder::new(body, flate2::Compression::Default)),
+        }
+    }
+}
+
+/// Picks the encoding with the highest q-value from an `Accept-Encoding`
+/// header, brotli wins ties. `*` counts for both, `q=0` rules an encoding out.
+pub fn negotiate(accept_encoding: &str) -> Option<Encoding> {
+    let mut brotli = None;
+    let mut gzip = None;
+    let mut any = None;
+    for item in accept_encoding.split(',') {
+        let mut parts = item.split(';');
+        let coding = parts.next().unwrap().trim().to_ascii_lowercase();
+        let q = parts.filter_map(|p| {
+            let p = p.trim();
+            if p.starts_with("q=") { p[2..].parse::<f32>().ok() } else { None }
+        }).next().unwrap_or(1.0);
+        match coding.as_slice() {
+            "br"              => { brotli = Some(q); },
+            "gzip" | "x-gzip" => { gzip = Some(q); },
+            "*"               => { any = Some(q); },
+            _                 => { },
+        }
+    }
+    let brotli = brotli.or(any).unwrap_or(0.0);
+    let gzip = gzip.or(any).unwrap_or(0.0);
+    if brotli <= 0.0 && gzip <= 0.0 {
+        None
+    } else if brotli >= gzip {
+        Some(Encoding::Brotli)
+    } else {
+        Some(Encoding::Gzip)
+    }
+}
+
+/// Compresses response bodies of at least `min_size` bytes with the
+/// encoding negotiated from `Accept-Encoding`. Bodies without a known length
+/// are always compressed.
+pub struct CompressionMiddleware {
+    pub min_size: u64,
+}
+
+impl AfterMiddleware for CompressionMiddleware {
+    fn after(&self, req: &mut Request, res: Response) -> IronResult<Response> {
+        let mut response = res;
+        if response.body.is_none() || response.status == Some(status::NotModified) || response.headers.get_raw("Content-Encoding").is_some() {
+            return Ok(response);
+        }
+        match response.headers.get::<headers::ContentLength>() {
+            Some(&headers::ContentLength(length)) if length < self.min_size => { return Ok(response); },
+            _ => { },
+        }
+        let encoding = match header_value(req, "Accept-Encoding").and_then(|v| negotiate(v.as_slice())) {
+            Some(encoding) => encoding,
+            None           => { return Ok(response); },
+        };
+
+        let body = response.body.take().unwrap();
+        response.body = Some(encoding.encode(body));
+        response.headers.remove::<headers::ContentLength>();
+        response.headers.set_raw("Content-Encoding", vec![encoding.name().as_bytes().to_vec()]);
+        append_vary(&mut response, "Accept-Encoding");
+
+        // The compressed bytes differ from the uncompressed ones, so the ETag
+        // can only be a weak one now.
+        let etag = match response.headers.get_raw("ETag").and_then(|v| str::from_utf8(v[0].as_slice()).ok()) {
+            Some(etag) if !etag.starts_with("W/") => Some(format!("W/{}", etag)),
+            _                                     => None,
+        };
+        if let Some(etag) = etag {
+            response.headers.set_raw("ETag", vec![etag.into_bytes()]);
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Cursor, Read};
+    use flate2::read::GzDecoder;
+    use brotli2::read::BrotliDecoder;
+    use super::{negotiate, Encoding};
+
+    #[test]
+    fn prefers_brotli_on_ties() {
+        assert_eq!(negotiate("gzip, deflate, br"), Some(Encoding::Brotli));
+        assert_eq!(negotiate("*"), Some(Encoding::Brotli));
+    }
+
+    #[test]
+    fn respects_q_values() {
+        assert_eq!(negotiate("br;q=0.5, gzip"), Some(Encoding::Gzip));
+        assert_eq!(negotiate("gzip;q=0, br;q=0"), None);
+        assert_eq!(negotiate("*;q=0.1, br;q=0"), Some(Encoding::Gzip));
+        assert_eq!(negotiate("identity"), None);
+    }
+
+    #[test]
+    fn encoders_round_trip() {
+        let body: String = (0..1000).map(|_| "[{\"time\": 580325400}]").collect();
+
+        let mut gzipped = String::new();
+        GzDecoder::new(Encoding::Gzip.encode(Box::new(Cursor::new(body.clone().into_bytes())))).unwrap().read_to_string(&mut gzipped).unwrap();
+        assert_eq!(gzipped, body);
+
+        let mut brotli = String::new();
+        BrotliDecoder::new(Encoding::Brotli.encode(Box::new(Cursor::new(body.clone().into_bytes())))).read_to_string(&mut brotli).unwrap();
+        assert_eq!(brotli, body);
+    }
+}
@@ -0,0 +1,84 @@
+use iron::{Request, Response, IronResult, AfterMiddleware, Handler};
+use iron::status;
+use iron::headers;
+use iron::method::Method;
+
+use cache::{append_vary, header_value};
+
+const EXPOSED_HEADERS: &'static str = "X-Total-Count, X-Page, X-Per-Page, ETag, Last-Modified, X-Request-Id";
+const ALLOWED_HEADERS: &'static str = "Accept, Content-Type, If-None-Match, If-Modified-Since, X-Request-Id";
+// Seconds browsers may cache a preflight response.
+const PREFLIGHT_MAX_AGE: u32 = 86400;
+
+/// Origins allowed to call the API from a browser, `*` allows every origin.
+pub struct CorsMiddleware {
+    allowed_origins: Vec<String>,
+}
+
+impl CorsMiddleware {
+    /// Takes a comma separated list as in `CORS_ALLOWED_ORIGINS`.
+    pub fn from_list(origins: &str) -> CorsMiddleware {
+        CorsMiddleware {
+            allowed_origins: origins.split(',').map(|o| o.trim().trim_right_matches('/').to_string()).filter(|o| !o.is_empty()).collect(),
+        }
+    }
+
+    fn allows(&self, origin: &str) -> bool {
+        self.allowed_origins.iter().any(|o| o.as_slice() == "*" || o.as_slice() == origin)
+    }
+}
+
+impl AfterMiddleware for CorsMiddleware {
+    fn after(&self, req: &mut Request, res: Response) -> IronResult<Response> {
+        let mut response = res;
+        append_vary(&mut response, "Origin");
+        let origin = match header_value(req, "Origin") {
+            Some(ref origin) if self.allows(origin.as_slice()) => origin.clone(),
+            _ => { return Ok(response); },
+        };
+
+        response.headers.set_raw("Access-Control-Allow-Origin", vec![origin.into_bytes()]);
+        response.headers.set_raw("Access-Control-Expose-Headers", vec![EXPOSED_HEADERS.as_bytes().to_vec()]);
+        Ok(response)
+    }
+}
+
+/// Answers `OPTIONS` for a route with the methods registered for it. The
+/// origin check happens in `CorsMiddleware` like for every other response.
+pub struct PreflightHandler {
+    pub allowed: Vec<Method>,
+}
+
+impl Handler for PreflightHandler {
+    fn handle(&self, _: &mut Request) -> IronResult<Response> {
+        let mut allowed = self.allowed.clone();
+        allowed.push(Method::Options);
+        let methods: Vec<String> = allowed.iter().map(|m| m.to_string()).collect();
+
+        let mut response = Response::with(status::NoContent);
+        response.headers.set(headers::Allow(allowed));
+        response.headers.set_raw("Access-Control-Allow-Methods", vec![methods.connect(", ").into_bytes()]);
+        response.headers.set_raw("Access-Control-Allow-Headers", vec![ALLOWED_HEADERS.as_bytes().to_vec()]);
+        response.headers.set_raw("Access-Control-Max-Age", vec![PREFLIGHT_MAX_AGE.to_string().into_bytes()]);
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CorsMiddleware;
+
+    #[test]
+    fn allows_listed_origins() {
+        let cors = CorsMiddleware::from_list("https://example.org, https://stats.example.org/");
+        assert!(cors.allows("https://example.org"));
+        assert!(cors.allows("https://stats.example.org"));
+        assert!(!cors.allows("https://evil.example.org"));
+    }
+
+    #[test]
+    fn wildcard_allows_every_origin() {
+        assert!(CorsMiddleware::from_list("*").allows("https://example.org"));
+        assert!(!CorsMiddleware::from_list("").allows("https://example.org"));
+    }
+}
@@ -7,13 +7,18 @@ pub mod wca_data {
     use std::collections::BTreeMap;
     use std::collections::HashSet;
     use std::collections::Bound::{Included, Unbounded};
+    use std::cmp::Ordering;
+    use std::hash::{Hash, Hasher, SipHasher};
     use rustc_serialize::Decodable;
     use rustc_serialize::Decoder;
-    use std::path::Path;
+    use std::path::{Path, PathBuf};
 
     pub type WcaId = String;
     pub type PuzzleId = String;
 
+    // Most buckets of a histogram, see `WCA::distribution`.
+    pub const MAX_BUCKETS: usize = 100;
+
     #[derive(PartialEq, Clone, Copy)]
     pub enum Gender {
         Male,
@@ -27,6 +32,22 @@ pub mod wca_data {
         Average,
     }
 
+    // Level of a regional record marker. World records are also continental
+    // and national records, continental records are also national records.
+    #[derive(Copy, PartialEq, PartialOrd)]
+    pub enum RecordLevel {
+        Country,
+        Continent,
+        World,
+    }
+
+    #[derive(Clone)]
+    pub enum Scope {
+        World,
+        Continent(String),
+        Country(String),
+    }
+
     impl Decodable for Gender {
         fn decode<D: Decoder>(d: &mut D) -> Result<Gender, D::Error> {
             match d.read_str() {
@@ -72,6 +93,14 @@ pub mod wca_data {
     pub struct Ranking {
         pub result: CompResult,
         pub competitor_id: WcaId,
+        pub rank: u32,
+    }
+
+    pub struct RankPosition<'a> {
+        pub ranking: &'a Ranking,
+        pub total: usize,
+        pub above: Option<&'a Ranking>,
+        pub below: Option<&'a Ranking>,
     }
 
     // TODO add puzzle enum
@@ -93,13 +122,303 @@ pub mod wca_data {
         pub average: Option<CompResult>,
     }
 
+    #[derive(RustcDecodable)]
+    struct ResultRow {
+        competition_id: String,
+        event_id: String,
+        round_id: String,
+        pos: u32,
+        best: i32,
+        average: i32,
+        person_name: String,
+        person_id: WcaId,
+        person_country_id: String,
+        format_id: String,
+        value1: i32,
+        value2: i32,
+        value3: i32,
+        value4: i32,
+        value5: i32,
+        regional_single_record: String,
+        regional_average_record: String,
+    }
+
+    // A single row of the results export. Times are in centiseconds, -1 is DNF,
+    // -2 is DNS and 0 means no result.
+    #[derive(RustcEncodable)]
+    pub struct CompetitionResult {
+        pub competition_id: String,
+        pub puzzle_id: PuzzleId,
+        pub round_id: String,
+        pub pos: u32,
+        pub best: i32,
+        pub average: i32,
+        pub competitor_id: WcaId,
+        pub competitor_name: String,
+        pub country: String,
+        pub format_id: String,
+        pub values: Vec<i32>,
+        pub regional_single_record: Option<String>,
+        pub regional_average_record: Option<String>,
+    }
+
+    #[derive(RustcDecodable)]
+    pub struct Competition {
+        pub id: String,
+        pub name: String,
+        pub city: String,
+        pub country: String,
+        pub information: String,
+        pub year: u32,
+        pub month: u32,
+        pub day: u32,
+        pub end_month: u32,
+        pub end_day: u32,
+    }
+
+    impl Competition {
+        pub fn date(&self) -> String {
+            format!("{:04}-{:02}-{:02}", self.year, self.month, self.day)
+        }
+
+        // Number of days between 1970-01-01 and the first day of the competition.
+        pub fn days_since_epoch(&self) -> i64 {
+            days_from_civil(self.year as i64, self.month as i64, self.day as i64)
+        }
+
+        // Same for the last day, competitions spanning new year end in the next year.
+        pub fn end_days_since_epoch(&self) -> i64 {
+            let year = if self.end_month < self.month { self.year + 1 } else { self.year };
+            days_from_civil(year as i64, self.end_month as i64, self.end_day as i64)
+        }
+    }
+
+    // Days since 1970-01-01 of a date in the proleptic Gregorian calendar.
+    pub fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+        let year = if month <= 2 { year - 1 } else { year };
+        let era = year / 400;
+        let year_of_era = year - era * 400;
+        let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146097 + day_of_era - 719468
+    }
+
+    #[derive(RustcEncodable, Clone, Copy)]
+    pub struct Medals {
+        pub gold: u32,
+        pub silver: u32,
+        pub bronze: u32,
+    }
+
+    impl Medals {
+        pub fn new() -> Medals {
+            Medals { gold: 0, silver: 0, bronze: 0 }
+        }
+
+        pub fn total(&self) -> u32 {
+            self.gold + self.silver + self.bronze
+        }
+
+        fn add(&mut self, pos: u32) {
+            match pos {
+                1 => { self.gold += 1; },
+                2 => { self.silver += 1; },
+                3 => { self.bronze += 1; },
+                _ => { },
+            }
+        }
+    }
+
+    pub struct CompetitorMedals<'a> {
+        pub total: Medals,
+        pub events: BTreeMap<&'a PuzzleId, Medals>,
+        pub podiums: Vec<&'a CompetitionResult>,
+    }
+
+    pub struct AttendedCompetition<'a> {
+        pub competition_id: &'a String,
+        pub competition: Option<&'a Competition>,
+        pub puzzle_ids: Vec<&'a PuzzleId>,
+    }
+
+    pub struct CompetitionEntry<'a> {
+        pub competitor_id: &'a WcaId,
+        pub puzzle_ids: Vec<&'a PuzzleId>,
+    }
+
+    // Two competitors who competed at the same competition.
+    pub struct Link<'a> {
+        pub from: &'a WcaId,
+        pub to: &'a WcaId,
+        pub competition_id: &'a String,
+    }
+
+    pub struct Round<'a> {
+        pub round_id: &'a String,
+        pub results: Vec<&'a CompetitionResult>,
+    }
+
+    pub struct EventResults<'a> {
+        pub puzzle_id: &'a PuzzleId,
+        pub rounds: Vec<Round<'a>>,
+    }
+
+    pub struct HistoricRecord<'a> {
+        pub result: &'a CompetitionResult,
+        pub time: i32,
+        pub region: String,
+        // None while the record has not been broken.
+        pub days_stood: Option<i64>,
+    }
+
+    pub struct PersonalBest<'a> {
+        pub result: &'a CompetitionResult,
+        pub single: Option<i32>,
+        pub average: Option<i32>,
+    }
+
+    #[derive(RustcDecodable)]
+    pub struct Country {
+        pub id: String,
+        pub name: String,
+        pub continent_id: String,
+    }
+
+    #[derive(RustcEncodable)]
+    pub struct EventRank {
+        pub puzzle_id: PuzzleId,
+        pub rank: u32,
+        pub penalty: bool,
+    }
+
+    #[derive(RustcEncodable)]
+    pub struct SumOfRanks {
+        pub competitor_id: WcaId,
+        pub rank: u32,
+        pub sum: u32,
+        pub events: Vec<EventRank>,
+    }
+
+    pub struct RegionalRecord<'a> {
+        pub time: u32,
+        pub holders: Vec<&'a WcaId>,
+    }
+
+    pub struct CurrentRecords<'a> {
+        pub puzzle_id: &'a PuzzleId,
+        pub single: Option<RegionalRecord<'a>>,
+        pub average: Option<RegionalRecord<'a>>,
+    }
+
+    #[derive(RustcEncodable)]
+    pub struct GenderBreakdown {
+        pub male: u32,
+        pub female: u32,
+        pub unknown: u32,
+    }
+
+    #[derive(RustcEncodable)]
+    pub struct CountryStats {
+        pub country_id: String,
+        pub competitors: u32,
+        pub genders: GenderBreakdown,
+        pub competitions: u32,
+        // Competitors who competed in the year, by year.
+        pub active_competitors: BTreeMap<u32, u32>,
+        // Competitors who have competed in the event, by event.
+        pub events: BTreeMap<PuzzleId, u32>,
+    }
+
+    impl CountryStats {
+        fn new(country_id: &String) -> CountryStats {
+            CountryStats {
+                country_id: country_id.clone(),
+                competitors: 0,
+                genders: GenderBreakdown { male: 0, female: 0, unknown: 0 },
+                competitions: 0,
+                active_competitors: BTreeMap::new(),
+                events: BTreeMap::new(),
+            }
+        }
+    }
+
+    // Bucket of a histogram, `to` is exclusive.
+    #[derive(RustcEncodable)]
+    pub struct Bucket {
+        pub from: u32,
+        pub to: u32,
+        pub count: usize,
+    }
+
+    #[derive(RustcEncodable)]
+    pub struct Distribution {
+        pub count: usize,
+        pub mean: f64,
+        pub p1: u32,
+        pub p10: u32,
+        pub p50: u32,
+        pub p90: u32,
+        pub histogram: Vec<Bucket>,
+    }
+
+    #[derive(RustcEncodable)]
+    pub struct KinchEventScore {
+        pub puzzle_id: PuzzleId,
+        pub score: f64,
+    }
+
+    #[derive(RustcEncodable)]
+    pub struct KinchScore {
+        pub competitor_id: WcaId,
+        pub rank: u32,
+        pub score: f64,
+        pub events: Vec<KinchEventScore>,
+    }
+
+    #[derive(RustcEncodable)]
+    pub struct ResultComparison {
+        pub first: Option<CompResult>,
+        pub second: Option<CompResult>,
+        pub leader: Option<WcaId>,
+        pub margin: Option<u32>,
+    }
+
+    #[derive(RustcEncodable)]
+    pub struct EventComparison {
+        pub puzzle_id: PuzzleId,
+        pub single: ResultComparison,
+        pub average: ResultComparison,
+    }
+
+    #[derive(RustcEncodable)]
+    pub struct Comparison {
+        pub first_id: WcaId,
+        pub second_id: WcaId,
+        pub first_score: u32,
+        pub second_score: u32,
+        pub events: Vec<EventComparison>,
+        pub shared_competitions: Vec<String>,
+    }
+
     pub struct WCA {
         pub persons: BTreeMap<WcaId, Competitor>,
         competitions: HashMap<WcaId, HashSet<String>>,
+        competitors_at: HashMap<String, HashSet<WcaId>>,
         records: HashMap<String, HashMap<String, Record>>,
         single_rankings: HashMap<PuzzleId, Vec<Ranking>>,
         average_rankings: HashMap<PuzzleId, Vec<Ranking>>,
+        single_positions: HashMap<WcaId, HashMap<PuzzleId, usize>>,
+        average_positions: HashMap<WcaId, HashMap<PuzzleId, usize>>,
         events: Vec<Event>,
+        countries: HashMap<String, Country>,
+        competition_details: HashMap<String, Competition>,
+        results: Vec<CompetitionResult>,
+        results_by_competitor: HashMap<WcaId, Vec<usize>>,
+        record_results: HashMap<PuzzleId, Vec<usize>>,
+        podium_results: Vec<usize>,
+        country_stats: HashMap<String, CountryStats>,
+        results_by_competition: HashMap<String, Vec<usize>>,
+        export_version: u64,
     }
 
     #[derive(RustcDecodable, RustcEncodable)]
@@ -119,10 +438,68 @@ pub mod wca_data {
             } else {
                 self.competitions.insert(id.clone(), HashSet::new());
             }
+            if self.competitors_at.contains_key(&comp_id) {
+            } else {
+                self.competitors_at.insert(comp_id.clone(), HashSet::new());
+            }
+            self.competitors_at.get_mut(&comp_id).unwrap().insert(id.clone());
+
             let set = self.competitions.get_mut(&id).unwrap();
             set.insert(comp_id);
         }
 
+        fn add_result(&mut self, result: CompetitionResult) {
+            if self.results_by_competitor.contains_key(&result.competitor_id) {
+            } else {
+                self.results_by_competitor.insert(result.competitor_id.clone(), vec![]);
+            }
+            self.results_by_competitor.get_mut(&result.competitor_id).unwrap().push(self.results.len());
+
+            if self.results_by_competition.contains_key(&result.competition_id) {
+            } else {
+                self.results_by_competition.insert(result.competition_id.clone(), vec![]);
+            }
+            self.results_by_competition.get_mut(&result.competition_id).unwrap().push(self.results.len());
+
+            if is_podium(&result) {
+                self.podium_results.push(self.results.len());
+            }
+            if result.regional_single_record.is_some() || result.regional_average_record.is_some() {
+                if self.record_results.contains_key(&result.puzzle_id) {
+                } else {
+                    self.record_results.insert(result.puzzle_id.clone(), vec![]);
+                }
+                self.record_results.get_mut(&result.puzzle_id).unwrap().push(self.results.len());
+            }
+            self.results.push(result);
+        }
+
+        // Orders results by the date of their competition and the round within it.
+        fn chronological_key<'b>(&self, result: &'b CompetitionResult) -> (u32, u32, u32, &'b str, u32) {
+            let (year, month, day) = match self.find_competition(&result.competition_id) {
+                Some(c) => (c.year, c.month, c.day),
+                None    => (0, 0, 0),
+            };
+            (year, month, day, result.competition_id.as_slice(), round_order(result.round_id.as_slice()))
+        }
+
+        // The keys are looked up once per result, not once per comparison.
+        fn sort_results_chronologically(&mut self) {
+            let (by_competitor, by_puzzle) = {
+                let keys: Vec<(u32, u32, u32, &str, u32)> = self.results.iter().map(|r| self.chronological_key(r)).collect();
+                let sorted = |positions: &Vec<usize>| {
+                    let mut sorted = positions.clone();
+                    sorted.sort_by(|a, b| keys[*a].cmp(&keys[*b]));
+                    sorted
+                };
+                let by_competitor: HashMap<WcaId, Vec<usize>> = self.results_by_competitor.iter().map(|(id, positions)| (id.clone(), sorted(positions))).collect();
+                let by_puzzle: HashMap<PuzzleId, Vec<usize>> = self.record_results.iter().map(|(id, positions)| (id.clone(), sorted(positions))).collect();
+                (by_competitor, by_puzzle)
+            };
+            self.results_by_competitor = by_competitor;
+            self.record_results = by_puzzle;
+        }
+
         fn update_competition_count_cache(&mut self) {
             for (id, competitor) in self.persons.iter_mut() {
                 match self.competitions.get(id) {
@@ -132,6 +509,52 @@ pub mod wca_data {
             }
         }
 
+        fn update_country_stats_cache(&mut self) {
+            let mut stats: HashMap<String, CountryStats> = HashMap::new();
+            for id in self.countries.keys() {
+                stats.insert(id.clone(), CountryStats::new(id));
+            }
+
+            for competitor in self.persons.values() {
+                let country_stats = match stats.get_mut(&competitor.country) {
+                    Some(s) => s,
+                    None    => { continue; },
+                };
+                country_stats.competitors += 1;
+                match competitor.gender {
+                    Gender::Male    => { country_stats.genders.male += 1; },
+                    Gender::Female  => { country_stats.genders.female += 1; },
+                    Gender::Unknown => { country_stats.genders.unknown += 1; },
+                }
+
+                let mut years = HashSet::new();
+                let mut puzzle_ids = HashSet::new();
+                for result in self.find_results_of(&competitor.id).into_iter() {
+                    match self.find_competition(&result.competition_id) {
+                        Some(c) => { years.insert(c.year); },
+                        None    => { },
+                    }
+                    puzzle_ids.insert(&result.puzzle_id);
+                }
+                for year in years.into_iter() {
+                    let count = country_stats.active_competitors.get(&year).map(|c| *c).unwrap_or(0);
+                    country_stats.active_competitors.insert(year, count + 1);
+                }
+                for puzzle_id in puzzle_ids.into_iter() {
+                    let count = country_stats.events.get(puzzle_id).map(|c| *c).unwrap_or(0);
+                    country_stats.events.insert(puzzle_id.clone(), count + 1);
+                }
+            }
+
+            for competition in self.competition_details.values() {
+                match stats.get_mut(&competition.country) {
+                    Some(s) => { s.competitions += 1; },
+                    None    => { },
+                }
+            }
+            self.country_stats = stats;
+        }
+
         fn add_single_record(&mut self, id: String, puzzle: String, time: u32) {
             if self.records.contains_key(&id.clone()) {
             } else {
@@ -147,7 +570,7 @@ pub mod wca_data {
                 self.single_rankings.insert(puzzle_id.clone(), vec![]);
             }
             let vec = self.single_rankings.get_mut(&puzzle_id).unwrap();
-            vec.push(Ranking { result: CompResult {time: best}, competitor_id: competitor_id.clone()});
+            vec.push(Ranking { result: CompResult {time: best}, competitor_id: competitor_id.clone(), rank: 0});
         }
 
         fn add_average_ranking(&mut self, puzzle_id: String, best: u32, competitor_id: String) {
@@ -156,7 +579,7 @@ pub mod wca_data {
                 self.average_rankings.insert(puzzle_id.clone(), vec![]);
             }
             let vec = self.average_rankings.get_mut(&puzzle_id).unwrap();
-            vec.push(Ranking { result: CompResult {time: best}, competitor_id: competitor_id.clone()});
+            vec.push(Ranking { result: CompResult {time: best}, competitor_id: competitor_id.clone(), rank: 0});
         }
 
         fn add_average_record(&mut self, id: String, puzzle: String, time: u32) {
@@ -183,6 +606,12 @@ pub mod wca_data {
             self.persons.get(id)
         }
 
+        // Looks up many competitors at once, in the order of the given IDs.
+        // None for IDs without a competitor.
+        pub fn find_competitors_by_ids(&self, ids: &Vec<String>) -> Vec<Option<&Competitor>> {
+            ids.iter().map(|id| self.persons.get(id)).collect()
+        }
+
         pub fn find_competitors(&self, query: &String) -> Vec<&Competitor> {
             self.persons
                 .range(Included(query), Unbounded)
@@ -206,6 +635,597 @@ pub mod wca_data {
             }
         }
 
+        pub fn find_rank_position(&self, competitor_id: &String, puzzle_id: &String, result_type: ResultType) -> Option<RankPosition> {
+            let positions = match result_type {
+                ResultType::Single  => &self.single_positions,
+                ResultType::Average => &self.average_positions,
+            };
+            let index = match positions.get(competitor_id).and_then(|p| p.get(puzzle_id)) {
+                Some(index) => *index,
+                None        => { return None; },
+            };
+            let rankings = self.find_rankings(puzzle_id, result_type).unwrap();
+
+            Some(RankPosition {
+                ranking: &rankings[index],
+                total: rankings.len(),
+                above: if index > 0 { rankings.get(index - 1) } else { None },
+                below: rankings.get(index + 1),
+            })
+        }
+
+        pub fn find_competition(&self, competition_id: &String) -> Option<&Competition> {
+            self.competition_details.get(competition_id)
+        }
+
+        // All results of a competitor in chronological order.
+        pub fn find_results_of(&self, competitor_id: &String) -> Vec<&CompetitionResult> {
+            match self.results_by_competitor.get(competitor_id) {
+                Some(positions) => positions.iter().map(|i| &self.results[*i]).collect(),
+                None            => vec![],
+            }
+        }
+
+        // Every result which improved the single or average personal best of the
+        // competitor in the given event.
+        pub fn find_progression(&self, competitor_id: &String, puzzle_id: &String) -> Option<Vec<PersonalBest>> {
+            if self.find_competitor(competitor_id).is_none() {
+                return None;
+            }
+
+            let mut progression = vec![];
+            let mut best_single = None;
+            let mut best_average = None;
+            for result in self.find_results_of(competitor_id).into_iter().filter(|r| r.puzzle_id == *puzzle_id) {
+                let single = improvement(best_single, result.best);
+                let average = improvement(best_average, result.average);
+                if single.is_some() || average.is_some() {
+                    best_single = single.or(best_single);
+                    best_average = average.or(best_average);
+                    progression.push(PersonalBest { result: result, single: single, average: average });
+                }
+            }
+            Some(progression)
+        }
+
+        fn record_region(&self, result: &CompetitionResult, level: RecordLevel) -> Option<String> {
+            match level {
+                RecordLevel::World     => Some("World".to_string()),
+                RecordLevel::Continent => self.find_country(&result.country).map(|c| c.continent_id.clone()),
+                RecordLevel::Country   => Some(result.country.clone()),
+            }
+        }
+
+        // History of the records of the given level built from the regional record
+        // markers of the results export. Records of different regions are kept
+        // apart, e.g. each country has its own history of national records.
+        pub fn find_record_history(&self, puzzle_id: &String, result_type: ResultType, level: RecordLevel) -> Vec<HistoricRecord> {
+            let positions = match self.record_results.get(puzzle_id) {
+                Some(positions) => positions,
+                None            => { return vec![]; },
+            };
+
+            let mut records: Vec<(&CompetitionResult, i32, String)> = vec![];
+            for result in positions.iter().map(|i| &self.results[*i]) {
+                let (marker, time) = match result_type {
+                    ResultType::Single  => (&result.regional_single_record, result.best),
+                    ResultType::Average => (&result.regional_average_record, result.average),
+                };
+                let marker_level = match *marker {
+                    Some(ref m) => record_level(m.as_slice()),
+                    None        => { continue; },
+                };
+                if marker_level < level {
+                    continue;
+                }
+                match self.record_region(result, level) {
+                    Some(region) => records.push((result, time, region)),
+                    None         => { },
+                }
+            }
+            // Several records can be set in the same round, the best one comes last.
+            records.sort_by(|&(a, a_time, _), &(b, b_time, _)|
+                match self.chronological_key(a).cmp(&self.chronological_key(b)) {
+                    Ordering::Equal => b_time.cmp(&a_time),
+                    ordering        => ordering,
+                }
+            );
+
+            // Going backwards, the record which broke each one is the last one seen in its region.
+            let days = |result: &CompetitionResult| self.find_competition(&result.competition_id).map(|c| c.days_since_epoch());
+            let mut broken_on: HashMap<String, Option<i64>> = HashMap::new();
+            let mut history: Vec<HistoricRecord> = records.into_iter().rev().map(|(result, time, region)| {
+                let start = days(result);
+                let days_stood = match (broken_on.get(&region), start) {
+                    (Some(&Some(end)), Some(start)) => Some(end - start),
+                    _                               => None,
+                };
+                broken_on.insert(region.clone(), start);
+                HistoricRecord { result: result, time: time, region: region, days_stood: days_stood }
+            }).collect();
+            history.reverse();
+            history
+        }
+
+        // Competitions of a competitor in chronological order together with the
+        // events the competitor took part in.
+        pub fn find_competitions_of(&self, competitor_id: &String) -> Option<Vec<AttendedCompetition>> {
+            if self.find_competitor(competitor_id).is_none() {
+                return None;
+            }
+
+            let mut competitions: Vec<AttendedCompetition> = vec![];
+            for result in self.find_results_of(competitor_id).into_iter() {
+                if competitions.last().map(|c| *c.competition_id != result.competition_id).unwrap_or(true) {
+                    competitions.push(AttendedCompetition {
+                        competition_id: &result.competition_id,
+                        competition: self.find_competition(&result.competition_id),
+                        puzzle_ids: vec![],
+                    });
+                }
+                let competition = competitions.last_mut().unwrap();
+                if !competition.puzzle_ids.contains(&&result.puzzle_id) {
+                    competition.puzzle_ids.push(&result.puzzle_id);
+                }
+            }
+            for competition in competitions.iter_mut() {
+                competition.puzzle_ids.sort_by(|a, b| self.event_order(*a).cmp(&self.event_order(*b)));
+            }
+            Some(competitions)
+        }
+
+        // Everyone who competed at a competition, ordered by WCA id, together with
+        // the events they entered.
+        pub fn find_competitors_at(&self, competition_id: &String) -> Option<Vec<CompetitionEntry>> {
+            let competitor_ids = match self.competitors_at.get(competition_id) {
+                Some(ids) => ids,
+                None      => {
+                    return self.find_competition(competition_id).map(|_| vec![]);
+                },
+            };
+
+            let mut puzzle_ids: HashMap<&WcaId, Vec<&PuzzleId>> = HashMap::new();
+            for result in self.results_by_competition.get(competition_id).unwrap().iter().map(|i| &self.results[*i]) {
+                if puzzle_ids.contains_key(&result.competitor_id) {
+                } else {
+                    puzzle_ids.insert(&result.competitor_id, vec![]);
+                }
+                let events = puzzle_ids.get_mut(&result.competitor_id).unwrap();
+                if !events.contains(&&result.puzzle_id) {
+                    events.push(&result.puzzle_id);
+                }
+            }
+
+            let mut entries: Vec<CompetitionEntry> = competitor_ids.iter().map(|id| {
+                let mut events = puzzle_ids.remove(id).unwrap_or(vec![]);
+                events.sort_by(|a, b| self.event_order(*a).cmp(&self.event_order(*b)));
+                CompetitionEntry { competitor_id: id, puzzle_ids: events }
+            }).collect();
+            entries.sort_by(|a, b| a.competitor_id.cmp(b.competitor_id));
+            Some(entries)
+        }
+
+        // Competitors who competed together with the given competitor and the
+        // number of competitions they shared, most frequent first.
+        pub fn find_co_competitors(&self, competitor_id: &String) -> Option<Vec<(&WcaId, u32)>> {
+            if self.find_competitor(competitor_id).is_none() {
+                return None;
+            }
+
+            let mut counts: HashMap<&WcaId, u32> = HashMap::new();
+            for competition_id in self.competitions.get(competitor_id).iter().flat_map(|c| c.iter()) {
+                for other in self.competitors_at.get(competition_id).unwrap().iter().filter(|id| *id != competitor_id) {
+                    let count = counts.get(other).map(|c| *c).unwrap_or(0);
+                    counts.insert(other, count + 1);
+                }
+            }
+
+            let mut co_competitors: Vec<(&WcaId, u32)> = counts.into_iter().collect();
+            co_competitors.sort_by(|&(a_id, a), &(b_id, b)|
+                match b.cmp(&a) {
+                    Ordering::Equal => a_id.cmp(b_id),
+                    ordering        => ordering,
+                }
+            );
+            Some(co_competitors)
+        }
+
+        // Visits everyone who shares a competition with the frontier and returns
+        // the newly visited competitors. `visited` remembers through which
+        // competitor and competition someone has been reached.
+        fn expand<'a>(&'a self,
+                      frontier: &Vec<&'a WcaId>,
+                      visited: &mut HashMap<&'a WcaId, Option<(&'a WcaId, &'a String)>>,
+                      visited_competitions: &mut HashSet<&'a String>) -> Vec<&'a WcaId> {
+            let mut next = vec![];
+            for id in frontier.iter() {
+                for competition_id in self.competitions.get(*id).iter().flat_map(|c| c.iter()) {
+                    if visited_competitions.contains(competition_id) {
+                        continue;
+                    }
+                    visited_competitions.insert(competition_id);
+                    for other in self.competitors_at.get(competition_id).unwrap().iter() {
+                        if !visited.contains_key(other) {
+                            visited.insert(other, Some((*id, competition_id)));
+                            next.push(other);
+                        }
+                    }
+                }
+            }
+            next
+        }
+
+        // Shortest chain of competitors linking two competitors where each two
+        // neighbours in the chain competed at the same competition. The graph
+        // is the relation between competitors and competitions, searched from
+        // both ends at once.
+        pub fn find_connection(&self, first_id: &String, second_id: &String) -> Option<Vec<Link>> {
+            let (first, second) = match (self.find_competitor(first_id), self.find_competitor(second_id)) {
+                (Some(a), Some(b)) => (&a.id, &b.id),
+                _                  => { return None; },
+            };
+            if first == second {
+                return Some(vec![]);
+            }
+
+            let mut forward: HashMap<&WcaId, Option<(&WcaId, &String)>> = HashMap::new();
+            let mut backward: HashMap<&WcaId, Option<(&WcaId, &String)>> = HashMap::new();
+            let mut forward_competitions = HashSet::new();
+            let mut backward_competitions = HashSet::new();
+            forward.insert(first, None);
+            backward.insert(second, None);
+            let mut forward_frontier = vec![first];
+            let mut backward_frontier = vec![second];
+
+            while !forward_frontier.is_empty() && !backward_frontier.is_empty() {
+                let meeting = if forward_frontier.len() <= backward_frontier.len() {
+                    forward_frontier = self.expand(&forward_frontier, &mut forward, &mut forward_competitions);
+                    forward_frontier.iter().find(|id| backward.contains_key(**id)).map(|id| *id)
+                } else {
+                    backward_frontier = self.expand(&backward_frontier, &mut backward, &mut backward_competitions);
+                    backward_frontier.iter().find(|id| forward.contains_key(**id)).map(|id| *id)
+                };
+
+                match meeting {
+                    Some(meeting) => {
+                        let mut chain = vec![];
+                        let mut current = meeting;
+                        while let Some(&Some((previous, competition_id))) = forward.get(current) {
+                            chain.push(Link { from: previous, to: current, competition_id: competition_id });
+                            current = previous;
+                        }
+                        chain.reverse();
+
+                        let mut current = meeting;
+                        while let Some(&Some((next, competition_id))) = backward.get(current) {
+                            chain.push(Link { from: current, to: next, competition_id: competition_id });
+                            current = next;
+                        }
+                        return Some(chain);
+                    },
+                    None => { },
+                }
+            }
+            None
+        }
+
+        fn event_order(&self, puzzle_id: &String) -> usize {
+            self.events.iter().position(|e| e.id == *puzzle_id).unwrap_or(self.events.len())
+        }
+
+        // Results of a competition grouped by event and round. Events are in the
+        // order of the events export, rounds in the order they were held and
+        // results in placement order.
+        pub fn find_competition_results(&self, competition_id: &String) -> Option<Vec<EventResults>> {
+            let positions = match self.results_by_competition.get(competition_id) {
+                Some(positions) => positions,
+                None            => {
+                    return self.find_competition(competition_id).map(|_| vec![]);
+                },
+            };
+
+            let mut results: Vec<&CompetitionResult> = positions.iter().map(|i| &self.results[*i]).collect();
+            results.sort_by(|a, b|
+                (self.event_order(&a.puzzle_id), round_order(a.round_id.as_slice()), a.pos)
+                    .cmp(&(self.event_order(&b.puzzle_id), round_order(b.round_id.as_slice()), b.pos))
+            );
+
+            let mut events: Vec<EventResults> = vec![];
+            for result in results.into_iter() {
+                if events.last().map(|e| *e.puzzle_id != result.puzzle_id).unwrap_or(true) {
+                    events.push(EventResults { puzzle_id: &result.puzzle_id, rounds: vec![] });
+                }
+                let event = events.last_mut().unwrap();
+                if event.rounds.last().map(|r| *r.round_id != result.round_id).unwrap_or(true) {
+                    event.rounds.push(Round { round_id: &result.round_id, results: vec![] });
+                }
+                event.rounds.last_mut().unwrap().results.push(result);
+            }
+            Some(events)
+        }
+
+        pub fn find_medals(&self, competitor_id: &String) -> Option<CompetitorMedals> {
+            if self.find_competitor(competitor_id).is_none() {
+                return None;
+            }
+
+            let mut medals = CompetitorMedals { total: Medals::new(), events: BTreeMap::new(), podiums: vec![] };
+            for result in self.find_results_of(competitor_id).into_iter().filter(|r| is_podium(*r)) {
+                medals.total.add(result.pos);
+                if medals.events.contains_key(&result.puzzle_id) {
+                } else {
+                    medals.events.insert(&result.puzzle_id, Medals::new());
+                }
+                medals.events.get_mut(&result.puzzle_id).unwrap().add(result.pos);
+                medals.podiums.push(result);
+            }
+            Some(medals)
+        }
+
+        // Medals per country, ordered by gold, then silver, then bronze medals.
+        // Podiums count for the country the competitor represented at the time.
+        pub fn medal_table(&self) -> Vec<(&String, Medals)> {
+            self.medal_table_by(|result| Some(&result.country))
+        }
+
+        // Medals per competitor of a country, ordered like the medal table.
+        pub fn medal_table_of_country(&self, country_id: &String) -> Vec<(&WcaId, Medals)> {
+            self.medal_table_by(|result| if result.country == *country_id { Some(&result.competitor_id) } else { None })
+        }
+
+        fn medal_table_by<'a, F>(&'a self, key: F) -> Vec<(&'a String, Medals)> where F: Fn(&'a CompetitionResult) -> Option<&'a String> {
+            let mut table: HashMap<&String, Medals> = HashMap::new();
+            for result in self.podium_results.iter().map(|i| &self.results[*i]) {
+                match key(result) {
+                    Some(k) => {
+                        if table.contains_key(k) {
+                        } else {
+                            table.insert(k, Medals::new());
+                        }
+                        table.get_mut(k).unwrap().add(result.pos);
+                    },
+                    None => { },
+                }
+            }
+
+            let mut table: Vec<(&String, Medals)> = table.into_iter().collect();
+            table.sort_by(|&(a_key, a), &(b_key, b)|
+                match (b.gold, b.silver, b.bronze).cmp(&(a.gold, a.silver, a.bronze)) {
+                    Ordering::Equal => a_key.cmp(b_key),
+                    ordering        => ordering,
+                }
+            );
+            table
+        }
+
+        pub fn find_country(&self, country_id: &String) -> Option<&Country> {
+            self.countries.get(country_id)
+        }
+
+        pub fn find_country_stats(&self, country_id: &String) -> Option<&CountryStats> {
+            self.country_stats.get(country_id)
+        }
+
+        pub fn find_countries(&self) -> Vec<&Country> {
+            let mut countries: Vec<&Country> = self.countries.values().collect();
+            countries.sort_by(|a, b| a.id.cmp(&b.id));
+            countries
+        }
+
+        fn regional_record(&self, puzzle_id: &String, result_type: ResultType, scope: &Scope) -> Option<RegionalRecord> {
+            let rankings = match self.find_rankings(puzzle_id, result_type) {
+                Some(rankings) => rankings,
+                None           => { return None; },
+            };
+            let mut in_scope = rankings.iter().filter(|r|
+                self.find_competitor(&r.competitor_id).map(|c| self.in_scope(c, scope)).unwrap_or(false)
+            );
+
+            match in_scope.next() {
+                Some(first) => {
+                    let mut holders = vec![&first.competitor_id];
+                    holders.extend(in_scope.take_while(|r| r.result.time == first.result.time).map(|r| &r.competitor_id));
+                    Some(RegionalRecord { time: first.result.time, holders: holders })
+                },
+                None => None,
+            }
+        }
+
+        // The current record holders of the scope for every event which has been
+        // competed in by someone of the scope. Ties list several holders.
+        pub fn current_records(&self, scope: &Scope) -> Vec<CurrentRecords> {
+            self.events.iter().filter_map(|e| {
+                match self.regional_record(&e.id, ResultType::Single, scope) {
+                    Some(single) => Some(CurrentRecords {
+                        puzzle_id: &e.id,
+                        single: Some(single),
+                        average: self.regional_record(&e.id, ResultType::Average, scope),
+                    }),
+                    None => None,
+                }
+            }).collect()
+        }
+
+        pub fn in_scope(&self, competitor: &Competitor, scope: &Scope) -> bool {
+            match *scope {
+                Scope::World                      => true,
+                Scope::Country(ref country_id)    => competitor.country == *country_id,
+                Scope::Continent(ref continent_id) => {
+                    self.find_country(&competitor.country).map(|c| c.continent_id == *continent_id).unwrap_or(false)
+                },
+            }
+        }
+
+        // Returns the rankings of competitors within the scope together with their
+        // rank inside the scope (equal results share a rank).
+        pub fn find_rankings_in(&self, puzzle_id: &String, result_type: ResultType, scope: &Scope) -> Vec<(u32, &Ranking)> {
+            let mut result = vec![];
+            let rankings = match self.find_rankings(puzzle_id, result_type) {
+                Some(rankings) => rankings,
+                None           => { return result; },
+            };
+
+            let mut rank = 0;
+            let mut previous_time = None;
+            for ranking in rankings.iter() {
+                match self.find_competitor(&ranking.competitor_id) {
+                    Some(c) if self.in_scope(c, scope) => { },
+                    _                                  => { continue; },
+                }
+                if previous_time != Some(ranking.result.time) {
+                    rank = result.len() as u32 + 1;
+                    previous_time = Some(ranking.result.time);
+                }
+                result.push((rank, ranking));
+            }
+            result
+        }
+
+        // Statistics over the personal records in an event, optionally restricted to
+        // competitors of one gender. The histogram splits the range between the
+        // best and the worst result into 1 to MAX_BUCKETS equally wide buckets.
+        pub fn distribution(&self, puzzle_id: &String, result_type: ResultType, gender: Option<Gender>, scope: &Scope, buckets: usize) -> Option<Distribution> {
+            let times: Vec<u32> = self.find_rankings_in(puzzle_id, result_type, scope).iter()
+                .filter(|&&(_, r)| gender.map(|g| self.find_competitor(&r.competitor_id).unwrap().gender == g).unwrap_or(true))
+                .map(|&(_, r)| r.result.time)
+                .collect();
+            if times.is_empty() || buckets == 0 || buckets > MAX_BUCKETS {
+                return None;
+            }
+
+            let count = times.len();
+            let percentile = |p: f64| {
+                let rank = (p * count as f64 / 100.0).ceil() as usize;
+                times[if rank > 0 { rank - 1 } else { 0 }]
+            };
+
+            let min = times[0];
+            let max = times[count - 1];
+            let width = (max - min) / buckets as u32 + 1;
+            let mut histogram: Vec<Bucket> = (0..buckets).map(|i| Bucket {
+                from: min + i as u32 * width,
+                to: min + (i as u32 + 1) * width,
+                count: 0,
+            }).collect();
+            for time in times.iter() {
+                histogram[((*time - min) / width) as usize].count += 1;
+            }
+
+            Some(Distribution {
+                count: count,
+                mean: times.iter().fold(0.0, |sum, t| sum + *t as f64) / count as f64,
+                p1: percentile(1.0),
+                p10: percentile(10.0),
+                p50: percentile(50.0),
+                p90: percentile(90.0),
+                histogram: histogram,
+            })
+        }
+
+        // Sums up the ranks of every competitor over the given events. Events a
+        // competitor has no result in count as the number of ranked competitors
+        // in that event plus one.
+        pub fn sum_of_ranks(&self, puzzle_ids: &Vec<PuzzleId>, result_type: ResultType, scope: &Scope) -> Vec<SumOfRanks> {
+            let mut ranks_per_event: Vec<(&PuzzleId, HashMap<&WcaId, u32>, u32)> = vec![];
+            let mut competitor_ids: HashSet<&WcaId> = HashSet::new();
+
+            for puzzle_id in puzzle_ids.iter() {
+                let rankings = self.find_rankings_in(puzzle_id, result_type, scope);
+                let penalty = rankings.len() as u32 + 1;
+                let mut ranks = HashMap::new();
+                for &(rank, ranking) in rankings.iter() {
+                    ranks.insert(&ranking.competitor_id, rank);
+                    competitor_ids.insert(&ranking.competitor_id);
+                }
+                ranks_per_event.push((puzzle_id, ranks, penalty));
+            }
+
+            let mut result: Vec<SumOfRanks> = competitor_ids.iter().map(|id| {
+                let events: Vec<EventRank> = ranks_per_event.iter().map(|&(puzzle_id, ref ranks, penalty)| {
+                    match ranks.get(id) {
+                        Some(rank) => EventRank { puzzle_id: puzzle_id.clone(), rank: *rank, penalty: false },
+                        None       => EventRank { puzzle_id: puzzle_id.clone(), rank: penalty, penalty: true },
+                    }
+                }).collect();
+                SumOfRanks {
+                    competitor_id: (*id).clone(),
+                    rank: 0,
+                    sum: events.iter().fold(0, |sum, e| sum + e.rank),
+                    events: events,
+                }
+            }).collect();
+
+            result.sort_by(|a, b|
+                match a.sum.cmp(&b.sum) {
+                    Ordering::Equal => a.competitor_id.cmp(&b.competitor_id),
+                    ordering        => ordering,
+                }
+            );
+            let mut rank = 0;
+            let mut previous_sum = None;
+            for (i, sor) in result.iter_mut().enumerate() {
+                if previous_sum != Some(sor.sum) {
+                    rank = i as u32 + 1;
+                    previous_sum = Some(sor.sum);
+                }
+                sor.rank = rank;
+            }
+            result
+        }
+
+        // Kinch scores compare the personal records of a competitor with the
+        // records of the scope, 100 meaning the competitor holds the record.
+        // The overall score is the mean over all given events.
+        pub fn kinch_ranks(&self, puzzle_ids: &Vec<PuzzleId>, scope: &Scope) -> Vec<KinchScore> {
+            let mut scope_records: Vec<(&PuzzleId, Option<u32>, Option<u32>)> = vec![];
+            let mut competitor_ids: HashSet<&WcaId> = HashSet::new();
+
+            for puzzle_id in puzzle_ids.iter() {
+                let singles = self.find_rankings_in(puzzle_id, ResultType::Single, scope);
+                let averages = self.find_rankings_in(puzzle_id, ResultType::Average, scope);
+                for &(_, ranking) in singles.iter() {
+                    competitor_ids.insert(&ranking.competitor_id);
+                }
+                scope_records.push((puzzle_id,
+                                    singles.first().map(|&(_, r)| r.result.time),
+                                    averages.first().map(|&(_, r)| r.result.time)));
+            }
+
+            let no_records = HashMap::new();
+            let mut result: Vec<KinchScore> = competitor_ids.iter().map(|id| {
+                let records = self.find_records(*id).unwrap_or(&no_records);
+                let events: Vec<KinchEventScore> = scope_records.iter().map(|&(puzzle_id, single, average)| {
+                    KinchEventScore {
+                        puzzle_id: puzzle_id.clone(),
+                        score: kinch_event_score(puzzle_id.as_slice(), records.get(puzzle_id), single, average),
+                    }
+                }).collect();
+                KinchScore {
+                    competitor_id: (*id).clone(),
+                    rank: 0,
+                    score: events.iter().fold(0.0, |sum, e| sum + e.score) / (events.len() as f64),
+                    events: events,
+                }
+            }).collect();
+
+            result.sort_by(|a, b|
+                match descending_scores(a.score, b.score) {
+                    Ordering::Equal => a.competitor_id.cmp(&b.competitor_id),
+                    ordering        => ordering,
+                }
+            );
+            let mut rank = 0;
+            let mut previous_score = None;
+            for (i, kinch) in result.iter_mut().enumerate() {
+                if previous_score != Some(kinch.score) {
+                    rank = i as u32 + 1;
+                    previous_score = Some(kinch.score);
+                }
+                kinch.rank = rank;
+            }
+            result
+        }
+
         pub fn find_rankings_for(&self, puzzle_id: &String, ids: Vec<String>) -> Vec<RecordWithCompetitor> {
             let mut result: Vec<RecordWithCompetitor> = ids.iter().filter_map(|id|
                 self.find_records(id).map(|r|{
@@ -223,18 +1243,156 @@ pub mod wca_data {
             result
         }
 
-        pub fn new(persons_path: &Path, results_path: &Path, records_single_path: &Path, records_average_path: &Path, events_path: &Path) -> Box<WCA> {
-            let mut w = Box::new(WCA { persons: BTreeMap::new(), competitions: HashMap::new(), records: HashMap::new(), single_rankings: HashMap::new(), average_rankings: HashMap::new(), events: Vec::new() });
-            load_persons(&mut *w, persons_path);
-            load_competitions(&mut *w, results_path);
-            load_single_records(&mut *w, records_single_path);
-            load_average_records(&mut *w, records_average_path);
-            load_events(&mut *w, events_path);
+        // Compares two competitors in every event either of them has a result in.
+        // Each event awards one point to whoever leads on single and one point to
+        // whoever leads on average.
+        pub fn compare(&self, first_id: &String, second_id: &String) -> Option<Comparison> {
+            if self.find_competitor(first_id).is_none() || self.find_competitor(second_id).is_none() {
+                return None;
+            }
+            let no_records = HashMap::new();
+            let first_records = self.find_records(first_id).unwrap_or(&no_records);
+            let second_records = self.find_records(second_id).unwrap_or(&no_records);
+
+            let mut comparison = Comparison {
+                first_id: first_id.clone(),
+                second_id: second_id.clone(),
+                first_score: 0,
+                second_score: 0,
+                events: vec![],
+                shared_competitions: vec![],
+            };
+
+            for event in self.events.iter() {
+                let first = first_records.get(&event.id);
+                let second = second_records.get(&event.id);
+                if first.is_none() && second.is_none() {
+                    continue;
+                }
+
+                let single = compare_results(first_id, second_id, first.map(|r| r.single), second.map(|r| r.single));
+                let average = compare_results(first_id, second_id, first.and_then(|r| r.average), second.and_then(|r| r.average));
+                for leader in [&single.leader, &average.leader].iter() {
+                    match **leader {
+                        Some(ref id) if id == first_id  => { comparison.first_score += 1; },
+                        Some(_)                         => { comparison.second_score += 1; },
+                        None                            => { },
+                    }
+                }
+                comparison.events.push(EventComparison { puzzle_id: event.id.clone(), single: single, average: average });
+            }
+
+            match (self.competitions.get(first_id), self.competitions.get(second_id)) {
+                (Some(a), Some(b)) => {
+                    comparison.shared_competitions = a.intersection(b).map(|c| c.clone()).collect();
+                    comparison.shared_competitions.sort();
+                },
+                _ => { },
+            }
+
+            Some(comparison)
+        }
+
+        // Fingerprint of the loaded export, changes whenever a competitor or a
+        // result differs.
+        fn update_export_version(&mut self) {
+            let mut hasher = SipHasher::new();
+            for c in self.persons.values() {
+                (&c.id, &c.name, &c.country).hash(&mut hasher);
+            }
+            for r in self.results.iter() {
+                (&r.competition_id, &r.puzzle_id, &r.round_id, &r.competitor_id, r.pos, r.best, r.average).hash(&mut hasher);
+            }
+            self.export_version = hasher.finish();
+        }
+
+        pub fn export_version(&self) -> String {
+            format!("{:016x}", self.export_version)
+        }
+
+        // The last day of the latest competition with results, the export is
+        // at least as recent as that. Days since 1970-01-01.
+        pub fn export_date(&self) -> Option<i64> {
+            self.results_by_competition.keys()
+                .filter_map(|id| self.find_competition(id))
+                .map(|c| c.end_days_since_epoch())
+                .max()
+        }
+
+        pub fn new(files: &ExportFiles) -> Box<WCA> {
+            let mut w = Box::new(WCA { persons: BTreeMap::new(), competitions: HashMap::new(), competitors_at: HashMap::new(), records: HashMap::new(), single_rankings: HashMap::new(), average_rankings: HashMap::new(), single_positions: HashMap::new(), average_positions: HashMap::new(), events: Vec::new(), countries: HashMap::new(), competition_details: HashMap::new(), results: Vec::new(), results_by_competitor: HashMap::new(), record_results: HashMap::new(), podium_results: Vec::new(), country_stats: HashMap::new(), results_by_competition: HashMap::new(), export_version: 0 });
+            load_persons(&mut *w, &files.persons);
+            load_competitions(&mut *w, &files.competitions);
+            load_results(&mut *w, &files.results);
+            load_single_records(&mut *w, &files.ranks_single);
+            load_average_records(&mut *w, &files.ranks_average);
+            load_events(&mut *w, &files.events);
+            load_countries(&mut *w, &files.countries);
             w.update_competition_count_cache();
+            w.update_country_stats_cache();
+            w.update_export_version();
             w
         }
     }
 
+    fn kinch_ratio(record: Option<u32>, personal: Option<u32>) -> f64 {
+        match (record, personal) {
+            (Some(r), Some(p)) if p > 0 => 100.0 * (r as f64) / (p as f64),
+            _                           => 0.0,
+        }
+    }
+
+    // Multi blind results are encoded as 0DDTTTTTMM where 99 - DD are the points
+    // and TTTTT the time in seconds. Unused time of the hour counts as a fraction
+    // of a point.
+    fn multi_blind_points(result: u32) -> f64 {
+        let points = 99 - (result / 10000000) % 100;
+        let seconds = (result / 100) % 100000;
+        let remaining = if seconds < 3600 { 3600 - seconds } else { 0 };
+        points as f64 + (remaining as f64) / 3600.0
+    }
+
+    fn kinch_event_score(puzzle_id: &str, record: Option<&Record>, scope_single: Option<u32>, scope_average: Option<u32>) -> f64 {
+        let single = record.map(|r| r.single.time);
+        let average = record.and_then(|r| r.average).map(|a| a.time);
+
+        match puzzle_id {
+            "333mbf" => {
+                match (scope_single, single) {
+                    // A record without points, e.g. 2/4 in over an hour, leaves nothing to compare with.
+                    (Some(r), Some(p)) if multi_blind_points(r) > 0.0 => 100.0 * multi_blind_points(p) / multi_blind_points(r),
+                    _                                                 => 0.0,
+                }
+            },
+            "333bf" | "444bf" | "555bf" | "333fm" => {
+                let by_single = kinch_ratio(scope_single, single);
+                let by_average = kinch_ratio(scope_average, average);
+                if by_single > by_average { by_single } else { by_average }
+            },
+            _ => kinch_ratio(scope_average, average),
+        }
+    }
+
+    // Highest score first, NaN scores last so sorting never panics.
+    fn descending_scores(a: f64, b: f64) -> Ordering {
+        match b.partial_cmp(&a) {
+            Some(ordering) => ordering,
+            None           => a.is_nan().cmp(&b.is_nan()),
+        }
+    }
+
+    fn compare_results(first_id: &String, second_id: &String, first: Option<CompResult>, second: Option<CompResult>) -> ResultComparison {
+        let (leader, margin) = match (first, second) {
+            (Some(a), Some(b)) if a.time < b.time => (Some(first_id.clone()), Some(b.time - a.time)),
+            (Some(a), Some(b)) if a.time > b.time => (Some(second_id.clone()), Some(a.time - b.time)),
+            (Some(_), Some(_))                    => (None, Some(0)),
+            (Some(_), None)                       => (Some(first_id.clone()), None),
+            (None, Some(_))                       => (Some(second_id.clone()), None),
+            (None, None)                          => (None, None),
+        };
+        ResultComparison { first: first, second: second, leader: leader, margin: margin }
+    }
+
     fn load_persons(w: &mut WCA, fp: &Path) {
         let mut rdr = csv::Reader::from_file(fp).unwrap().has_headers(true).delimiter(b'\t');
 
@@ -248,8 +1406,77 @@ pub mod wca_data {
         let mut rdr = csv::Reader::from_file(fp).unwrap().has_headers(true).delimiter(b'\t');
 
         for record in rdr.decode() {
-            let v: Vec<String> = record.unwrap();
-            w.visited_comp(v[7].clone(), v[0].clone());
+            let c: Competition = record.unwrap();
+            w.competition_details.insert(c.id.clone(), c);
+        }
+    }
+
+    fn non_empty(s: String) -> Option<String> {
+        if s.is_empty() { None } else { Some(s) }
+    }
+
+    fn load_results(w: &mut WCA, fp: &Path) {
+        let mut rdr = csv::Reader::from_file(fp).unwrap().has_headers(true).delimiter(b'\t');
+
+        for record in rdr.decode() {
+            let r: ResultRow = record.unwrap();
+            w.visited_comp(r.person_id.clone(), r.competition_id.clone());
+            w.add_result(CompetitionResult {
+                competition_id: r.competition_id,
+                puzzle_id: r.event_id,
+                round_id: r.round_id,
+                pos: r.pos,
+                best: r.best,
+                average: r.average,
+                competitor_id: r.person_id,
+                competitor_name: r.person_name,
+                country: r.person_country_id,
+                format_id: r.format_id,
+                values: vec![r.value1, r.value2, r.value3, r.value4, r.value5],
+                regional_single_record: non_empty(r.regional_single_record),
+                regional_average_record: non_empty(r.regional_average_record),
+            });
+        }
+        w.sort_results_chronologically();
+    }
+
+    // Top three of a final with a valid result.
+    fn is_podium(result: &CompetitionResult) -> bool {
+        (result.round_id == "f" || result.round_id == "c") && result.pos >= 1 && result.pos <= 3 && result.best > 0
+    }
+
+    fn record_level(marker: &str) -> RecordLevel {
+        match marker {
+            "WR" => RecordLevel::World,
+            "NR" => RecordLevel::Country,
+            _    => RecordLevel::Continent,
+        }
+    }
+
+    // Position of a round within a competition, see the round ids of the export.
+    fn round_order(round_id: &str) -> u32 {
+        match round_id {
+            "h" => 0,
+            "0" => 1,
+            "d" => 2,
+            "1" => 3,
+            "b" => 4,
+            "e" => 5,
+            "2" => 6,
+            "g" => 7,
+            "3" => 8,
+            "c" => 9,
+            "f" => 10,
+            _   => 11,
+        }
+    }
+
+    // Returns the result if it is valid and better than the current best.
+    fn improvement(current_best: Option<i32>, result: i32) -> Option<i32> {
+        match current_best {
+            _ if result <= 0                 => None,
+            Some(best) if best <= result     => None,
+            _                                => Some(result),
         }
     }
 
@@ -261,10 +1488,7 @@ pub mod wca_data {
             w.add_single_record(r.person_id.clone(), r.event_id.clone(), r.best);
             w.add_single_ranking(r.event_id, r.best, r.person_id);
         }
-        for (_, vec) in w.single_rankings.iter_mut() {
-            let mut s = vec.as_mut_slice();
-            s.sort_by(|a, b| a.result.time.cmp(&b.result.time));
-        }
+        w.single_positions = rank_and_index(&mut w.single_rankings);
     }
 
     fn load_average_records(w: &mut WCA, fp: &Path) {
@@ -275,6 +1499,35 @@ pub mod wca_data {
             w.add_average_record(r.person_id.clone(), r.event_id.clone(), r.best);
             w.add_average_ranking(r.event_id, r.best, r.person_id);
         }
+        w.average_positions = rank_and_index(&mut w.average_rankings);
+    }
+
+    // Sorts every ranking, assigns ranks (equal results share a rank) and
+    // returns the position of each competitor within the sorted rankings.
+    fn rank_and_index(rankings: &mut HashMap<PuzzleId, Vec<Ranking>>) -> HashMap<WcaId, HashMap<PuzzleId, usize>> {
+        let mut positions: HashMap<WcaId, HashMap<PuzzleId, usize>> = HashMap::new();
+
+        for (puzzle_id, vec) in rankings.iter_mut() {
+            let mut s = vec.as_mut_slice();
+            s.sort_by(|a, b| a.result.time.cmp(&b.result.time));
+
+            let mut rank = 0;
+            let mut previous_time = None;
+            for (i, ranking) in s.iter_mut().enumerate() {
+                if previous_time != Some(ranking.result.time) {
+                    rank = i as u32 + 1;
+                    previous_time = Some(ranking.result.time);
+                }
+                ranking.rank = rank;
+
+                if positions.contains_key(&ranking.competitor_id) {
+                } else {
+                    positions.insert(ranking.competitor_id.clone(), HashMap::new());
+                }
+                positions.get_mut(&ranking.competitor_id).unwrap().insert(puzzle_id.clone(), i);
+            }
+        }
+        positions
     }
 
     fn load_events(w: &mut WCA, fp: &Path) {
@@ -286,11 +1539,61 @@ pub mod wca_data {
         }
     }
 
-    pub fn build_from_files(persons_path: &Path,
-                            results_path: &Path,
-                            records_single_path: &Path,
-                            records_average_path: &Path,
-                            events_path: &Path) -> Box<WCA> {
-        WCA::new(persons_path, results_path, records_single_path, records_average_path, events_path)
+    fn load_countries(w: &mut WCA, fp: &Path) {
+        let mut rdr = csv::Reader::from_file(fp).unwrap().has_headers(true).delimiter(b'\t');
+
+        for record in rdr.decode() {
+            let c: Country = record.unwrap();
+            w.countries.insert(c.id.clone(), c);
+        }
+    }
+
+    /// Paths of the files of a WCA export.
+    pub struct ExportFiles {
+        pub persons: PathBuf,
+        pub results: PathBuf,
+        pub ranks_single: PathBuf,
+        pub ranks_average: PathBuf,
+        pub events: PathBuf,
+        pub countries: PathBuf,
+        pub competitions: PathBuf,
+    }
+
+    impl ExportFiles {
+        /// The files as named in the export zip, unpacked into `dir`.
+        pub fn in_dir(dir: &Path) -> ExportFiles {
+            ExportFiles {
+                persons: dir.join("WCA_export_Persons.tsv"),
+                results: dir.join("WCA_export_Results.tsv"),
+                ranks_single: dir.join("WCA_export_RanksSingle.tsv"),
+                ranks_average: dir.join("WCA_export_RanksAverage.tsv"),
+                events: dir.join("WCA_export_Events.tsv"),
+                countries: dir.join("WCA_export_Countries.tsv"),
+                competitions: dir.join("WCA_export_Competitions.tsv"),
+            }
+        }
+    }
+
+    pub fn build_from_files(files: &ExportFiles) -> Box<WCA> {
+        WCA::new(files)
+    }
+
+    #[cfg(test)]
+    mod test {
+        use std::cmp::Ordering;
+        use super::{CompResult, Record, descending_scores, kinch_event_score};
+
+        #[test]
+        fn kinch_multi_blind_without_record_points() {
+            let record = Record { single: CompResult { time: 990360002 }, average: None };
+            assert_eq!(kinch_event_score("333mbf", Some(&record), Some(990360002), None), 0.0);
+        }
+
+        #[test]
+        fn nan_scores_sort_last() {
+            assert_eq!(descending_scores(50.0, 0.0 / 0.0), Ordering::Less);
+            assert_eq!(descending_scores(0.0 / 0.0, 50.0), Ordering::Greater);
+            assert_eq!(descending_scores(100.0, 50.0), Ordering::Less);
+        }
     }
 }
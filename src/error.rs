@@ -0,0 +1,283 @@
+use std::collections::BTreeMap;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use rustc_serialize::json::Json;
+
+use iron::{Request, Response, IronResult, IronError, AfterMiddleware, Handler, Set};
+use iron::status;
+use iron::headers;
+use iron::method::Method;
+use iron::modifier::Modifier;
+use iron::typemap;
+use router::NoRoute;
+
+use logging::log_error;
+
+/// An error as returned to API clients:
+///
+/// `{"error": {"code": "not_found", "message": "competitor not found", "details": null}}`
+///
+/// Handlers respond with `Response::with(ApiError::not_found(..))`, the body
+/// itself is written by `ErrorMiddleware`.
+#[derive(Clone, Debug)]
+pub struct ApiError {
+    pub status: status::Status,
+    pub code: &'static str,
+    pub message: String,
+    pub details: Json,
+}
+
+impl ApiError {
+    pub fn new(status: status::Status, code: &'static str, message: String) -> ApiError {
+        ApiError { status: status, code: code, message: message, details: Json::Null }
+    }
+
+    pub fn not_found(what: &str) -> ApiError {
+        ApiError::new(status::NotFound, "not_found", format!("{} not found", what))
+    }
+
+    pub fn bad_request(message: &str) -> ApiError {
+        ApiError::new(status::BadRequest, "bad_request", message.to_string())
+    }
+
+    pub fn missing_parameter(name: &str) -> ApiError {
+        ApiError::new(status::BadRequest, "missing_parameter", format!("query parameter `{}` is required", name))
+            .with_details(Json::String(name.to_string()))
+    }
+
+    pub fn method_not_allowed(allowed: &Vec<Method>) -> ApiError {
+        let allowed: Vec<Json> = allowed.iter().map(|m| Json::String(m.to_string())).collect();
+        ApiError::new(status::MethodNotAllowed, "method_not_allowed", "method not allowed".to_string())
+            .with_details(Json::Array(allowed))
+    }
+
+    pub fn payload_too_large(limit: u64) -> ApiError {
+        ApiError::new(status::PayloadTooLarge, "payload_too_large", format!("body is larger than {} bytes", limit))
+            .with_details(Json::U64(limit))
+    }
+
+    pub fn internal() -> ApiError {
+        ApiError::new(status::InternalServerError, "internal_error", "internal server error".to_string())
+    }
+
+    /// The generic error for a status which was set without an `ApiError`.
+    pub fn from_status(status: status::Status) -> ApiError {
+        match status {
+            status::NotFound         => ApiError::not_found("resource"),
+            status::BadRequest       => ApiError::bad_request("bad request"),
+            status::MethodNotAllowed => ApiError::new(status, "method_not_allowed", "method not allowed".to_string()),
+            _                        => ApiError::internal(),
+        }
+    }
+
+    pub fn with_details(mut self, details: Json) -> ApiError {
+        self.details = details;
+        self
+    }
+
+    pub fn to_json(&self) -> Json {
+        let mut error = BTreeMap::new();
+        error.insert("code".to_string(), Json::String(self.code.to_string()));
+        error.insert("message".to_string(), Json::String(self.message.clone()));
+        error.insert("details".to_string(), self.details.clone());
+        let mut d = BTreeMap::new();
+        d.insert("error".to_string(), Json::Object(error));
+        Json::Object(d)
+    }
+}
+
+impl typemap::Key for ApiError { type Value = ApiError; }
+
+impl Modifier<Response> for ApiError {
+    fn modify(self, res: &mut Response) {
+        res.status = Some(self.status);
+        res.extensions.insert::<ApiError>(self);
+    }
+}
+
+/// Answers with a 405 on routes which exist, but not for the requested method.
+pub struct MethodNotAllowedHandler {
+    pub allowed: Vec<Method>,
+}
+
+impl Handler for MethodNotAllowedHandler {
+    fn handle(&self, _: &mut Request) -> IronResult<Response> {
+        let mut response = Response::with(ApiError::method_not_allowed(&self.allowed));
+        response.headers.set(headers::Allow(self.allowed.clone()));
+        Ok(response)
+    }
+}
+
+// A request handed to a worker of `CatchPanic`. The worker stores the
+// result in `result` and signals `done`, which is dropped without a signal
+// when the handler panics.
+struct Job {
+    request: &'static mut Request<'static, 'static>,
+    result: &'static mut Option<IronResult<Response>>,
+    done: mpsc::Sender<()>,
+}
+
+// The references are neither `Send` nor `'static`, see `CatchPanic::handle`
+// for why they may be used by a worker anyway.
+unsafe impl Send for Job {}
+
+/// Runs the wrapped handler on a pool of worker threads and answers with a
+/// 500 if it panics. Without it hyper drops the connection of a panicking
+/// handler, the after middleware never runs and the request is not logged.
+///
+/// Workers are reused, a new one is only started when none is idle, so the
+/// pool grows to the number of requests handled at the same time. A worker
+/// whose handler panicked ends.
+pub struct CatchPanic {
+    handler: Arc<Box<Handler>>,
+    jobs: Mutex<mpsc::Sender<Job>>,
+    queue: Arc<Mutex<mpsc::Receiver<Job>>>,
+    idle: Arc<AtomicUsize>,
+}
+
+impl CatchPanic {
+    pub fn new<H: Handler>(handler: H) -> CatchPanic {
+        let (jobs, queue) = mpsc::channel();
+        CatchPanic {
+            handler: Arc::new(Box::new(handler) as Box<Handler>),
+            jobs: Mutex::new(jobs),
+            queue: Arc::new(Mutex::new(queue)),
+            idle: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    // Takes an idle worker for the next job or starts a new one. A worker
+    // counts itself as idle again once it has finished a job.
+    fn reserve_worker(&self) {
+        loop {
+            let idle = self.idle.load(Ordering::SeqCst);
+            if idle == 0 {
+                break;
+            }
+            if self.idle.compare_and_swap(idle, idle - 1, Ordering::SeqCst) == idle {
+                return;
+            }
+        }
+
+        let handler = self.handler.clone();
+        let queue = self.queue.clone();
+        let idle = self.idle.clone();
+        thread::spawn(move || {
+            loop {
+                let job = match queue.lock().unwrap().recv() {
+                    Ok(job) => job,
+                    Err(_)  => { return; },
+                };
+                let Job { request, result, done } = job;
+                *result = Some(handler.handle(request));
+                let _ = done.send(());
+                idle.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+    }
+}
+
+impl Handler for CatchPanic {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        // This function does not return before the worker has signalled or
+        // dropped `done`, which happens after the handler has returned or
+        // unwound, so the borrows outlive their use like with a scoped thread.
+        let mut result = None;
+        let (done, finished) = mpsc::channel();
+        let job = Job {
+            request: unsafe { mem::transmute(&mut *req) },
+            result: unsafe { mem::transmute(&mut result) },
+            done: done,
+        };
+        self.reserve_worker();
+        let sent = self.jobs.lock().unwrap().send(job).is_ok();
+
+        match (sent, finished.recv(), result) {
+            (true, Ok(()), Some(result)) => result,
+            _ => {
+                log_error(req, "Handler panicked".to_string());
+                Ok(Response::with(ApiError::internal()))
+            },
+        }
+    }
+}
+
+/// Renders the `ApiError` of a response into its body. Error responses
+/// without a body and errors returned by handlers (including unknown
+/// routes) get the envelope as well, so clients never see an empty body.
+pub struct ErrorMiddleware;
+
+impl AfterMiddleware for ErrorMiddleware {
+    fn after(&self, _: &mut Request, res: Response) -> IronResult<Response> {
+        let mut response = res;
+        let error = match response.extensions.remove::<ApiError>() {
+            Some(error) => error,
+            None => match response.status {
+                Some(status) if status.is_client_error() || status.is_server_error() => {
+                    if response.body.is_some() {
+                        return Ok(response);
+                    }
+                    ApiError::from_status(status)
+                },
+                _ => { return Ok(response); },
+            },
+        };
+        response.set_mut((error.status, error.to_json().to_string()));
+        Ok(response)
+    }
+
+    fn catch(&self, req: &mut Request, err: IronError) -> IronResult<Response> {
+        let error = if err.is::<NoRoute>() {
+            ApiError::not_found("route")
+        } else {
+            log_error(req, format!("Error while handling request: {}", err));
+            ApiError::internal()
+        };
+        Ok(Response::with((error.status, error.to_json().to_string())))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpStream};
+    use iron::{Iron, Chain, Request, Response, IronResult, Handler};
+    use iron::status;
+    use super::{CatchPanic, ErrorMiddleware};
+
+    struct PanickingHandler;
+
+    impl Handler for PanickingHandler {
+        fn handle(&self, req: &mut Request) -> IronResult<Response> {
+            if req.url.path.connect("/") == "panic" {
+                panic!("Handler failed");
+            }
+            Ok(Response::with((status::Ok, "ok")))
+        }
+    }
+
+    fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn answers_panics_with_a_json_500() {
+        let mut chain = Chain::new(CatchPanic::new(PanickingHandler));
+        chain.link_after(ErrorMiddleware);
+        let server = Iron::new(chain).http("127.0.0.1:0").unwrap();
+
+        let panicked = get(server.socket, "/panic");
+        assert!(panicked.starts_with("HTTP/1.1 500"));
+        assert!(panicked.ends_with("{\"error\":{\"code\":\"internal_error\",\"details\":null,\"message\":\"internal server error\"}}"));
+
+        // The next request gets a new worker
+        assert!(get(server.socket, "/").ends_with("\r\n\r\nok"));
+    }
+}
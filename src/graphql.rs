@@ -0,0 +1,519 @@
+// A small GraphQL implementation covering what the API needs: queries made of
+// fields with aliases, literal arguments and nested selections. Fragments,
+// variables, directives and mutations are not supported.
+
+use std::collections::BTreeMap;
+use rustc_serialize::json::Json;
+
+use w::wca_data;
+use w::wca_data::WCA;
+
+pub const MAX_DEPTH: usize = 6;
+pub const MAX_COMPLEXITY: u64 = 20000;
+
+const DEFAULT_FIRST: i64 = 100;
+const MAX_FIRST: i64 = 1000;
+
+#[derive(Debug, PartialEq)]
+pub enum Value {
+    Str(String),
+    Int(i64),
+    Enum(String),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Field {
+    pub alias: Option<String>,
+    pub name: String,
+    pub arguments: Vec<(String, Value)>,
+    pub selection: Vec<Field>,
+}
+
+impl Field {
+    fn key(&self) -> String {
+        self.alias.clone().unwrap_or(self.name.clone())
+    }
+
+    fn argument(&self, name: &str) -> Option<&Value> {
+        self.arguments.iter().find(|&&(ref n, _)| n.as_slice() == name).map(|&(_, ref v)| v)
+    }
+
+    fn string_argument(&self, name: &str) -> Result<String, String> {
+        match self.argument(name) {
+            Some(&Value::Str(ref s)) | Some(&Value::Enum(ref s)) => Ok(s.clone()),
+            Some(_) => Err(format!("Argument \"{}\" of \"{}\" has to be a string", name, self.name)),
+            None    => Err(format!("Argument \"{}\" of \"{}\" is required", name, self.name)),
+        }
+    }
+
+    // Number of items a list field returns at most.
+    fn first(&self) -> Result<usize, String> {
+        match self.argument("first") {
+            Some(&Value::Int(n)) if n >= 0 && n <= MAX_FIRST => Ok(n as usize),
+            Some(_) => Err(format!("Argument \"first\" of \"{}\" has to be between 0 and {}", self.name, MAX_FIRST)),
+            None    => Ok(DEFAULT_FIRST as usize),
+        }
+    }
+
+    fn result_type(&self) -> Result<wca_data::ResultType, String> {
+        match self.argument("type") {
+            Some(&Value::Str(ref s)) | Some(&Value::Enum(ref s)) if *s == "single"  => Ok(wca_data::ResultType::Single),
+            Some(&Value::Str(ref s)) | Some(&Value::Enum(ref s)) if *s == "average" => Ok(wca_data::ResultType::Average),
+            None    => Ok(wca_data::ResultType::Single),
+            Some(_) => Err(format!("Argument \"type\" of \"{}\" has to be single or average", self.name)),
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    // Selection sets currently open, checked while parsing so deeply nested
+    // queries can't overflow the stack.
+    depth: usize,
+}
+
+impl Parser {
+    fn skip_ignored(&mut self) {
+        while self.pos < self.chars.len() {
+            match self.chars[self.pos] {
+                ' ' | '\t' | '\n' | '\r' | ',' => { self.pos += 1; },
+                '#' => {
+                    while self.pos < self.chars.len() && self.chars[self.pos] != '\n' {
+                        self.pos += 1;
+                    }
+                },
+                _ => { return; },
+            }
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_ignored();
+        self.chars.get(self.pos).map(|c| *c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.peek() {
+            Some(c) if c == expected => { self.pos += 1; Ok(()) },
+            Some(c) => Err(format!("Expected \"{}\" but found \"{}\" at {}", expected, c, self.pos)),
+            None    => Err(format!("Expected \"{}\" but the query ended", expected)),
+        }
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        self.skip_ignored();
+        let start = self.pos;
+        while self.pos < self.chars.len() && (self.chars[self.pos].is_alphanumeric() || self.chars[self.pos] == '_') {
+            self.pos += 1;
+        }
+        if start == self.pos || self.chars[start].is_numeric() {
+            return Err(format!("Expected a name at {}", start));
+        }
+        Ok(self.chars[start..self.pos].iter().map(|c| *c).collect())
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.peek() {
+            Some('"') => {
+                self.pos += 1;
+                let mut s = String::new();
+                loop {
+                    match self.chars.get(self.pos).map(|c| *c) {
+                        Some('"')  => { self.pos += 1; return Ok(Value::Str(s)); },
+                        Some('\\') => {
+                            match self.chars.get(self.pos + 1).map(|c| *c) {
+                                Some('n') => s.push('\n'),
+                                Some(c)   => s.push(c),
+                                None      => { return Err("Unterminated string".to_string()); },
+                            }
+                            self.pos += 2;
+                        },
+                        Some(c) => { s.push(c); self.pos += 1; },
+                        None    => { return Err("Unterminated string".to_string()); },
+                    }
+                }
+            },
+            Some(c) if c == '-' || c.is_numeric() => {
+                let start = self.pos;
+                self.pos += 1;
+                while self.pos < self.chars.len() && self.chars[self.pos].is_numeric() {
+                    self.pos += 1;
+                }
+                let digits: String = self.chars[start..self.pos].iter().map(|c| *c).collect();
+                digits.parse().map(|n| Value::Int(n)).map_err(|_| format!("Invalid number \"{}\"", digits))
+            },
+            Some(_) => self.name().map(|n| Value::Enum(n)),
+            None    => Err("Expected a value but the query ended".to_string()),
+        }
+    }
+
+    fn arguments(&mut self) -> Result<Vec<(String, Value)>, String> {
+        let mut arguments = vec![];
+        if self.peek() != Some('(') {
+            return Ok(arguments);
+        }
+        try!(self.expect('('));
+        while self.peek() != Some(')') {
+            let name = try!(self.name());
+            try!(self.expect(':'));
+            arguments.push((name, try!(self.value())));
+        }
+        try!(self.expect(')'));
+        Ok(arguments)
+    }
+
+    fn field(&mut self) -> Result<Field, String> {
+        let mut name = try!(self.name());
+        let mut alias = None;
+        if self.peek() == Some(':') {
+            try!(self.expect(':'));
+            alias = Some(name);
+            name = try!(self.name());
+        }
+        let arguments = try!(self.arguments());
+        let selection = if self.peek() == Some('{') { try!(self.selection_set()) } else { vec![] };
+        Ok(Field { alias: alias, name: name, arguments: arguments, selection: selection })
+    }
+
+    fn selection_set(&mut self) -> Result<Vec<Field>, String> {
+        try!(self.expect('{'));
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(format!("Query is nested deeper than {} levels", MAX_DEPTH));
+        }
+        let mut fields = vec![];
+        while self.peek() != Some('}') {
+            if self.peek().is_none() {
+                return Err("Expected \"}\" but the query ended".to_string());
+            }
+            fields.push(try!(self.field()));
+        }
+        try!(self.expect('}'));
+        self.depth -= 1;
+        if fields.is_empty() {
+            return Err("Selections must not be empty".to_string());
+        }
+        Ok(fields)
+    }
+
+    fn document(&mut self) -> Result<Vec<Field>, String> {
+        if self.peek() != Some('{') {
+            let operation = try!(self.name());
+            if operation != "query" {
+                return Err(format!("Unsupported operation \"{}\"", operation));
+            }
+            if self.peek() != Some('{') {
+                try!(self.name());
+            }
+        }
+        let fields = try!(self.selection_set());
+        match self.peek() {
+            Some(c) => Err(format!("Unexpected \"{}\" at {}", c, self.pos)),
+            None    => Ok(fields),
+        }
+    }
+}
+
+pub fn parse(query: &str) -> Result<Vec<Field>, String> {
+    Parser { chars: query.chars().collect(), pos: 0, depth: 0 }.document()
+}
+
+pub fn depth(fields: &Vec<Field>) -> usize {
+    fields.iter().map(|f| 1 + depth(&f.selection)).max().unwrap_or(0)
+}
+
+// Every field costs one, the selection of a list field costs as many times as
+// the list has items at most. Saturates instead of overflowing.
+pub fn complexity(fields: &Vec<Field>) -> u64 {
+    fields.iter().fold(0, |sum: u64, f| {
+        let items = if is_list(f.name.as_slice()) { f.first().unwrap_or(MAX_FIRST as usize) as u64 } else { 1 };
+        sum.saturating_add(1).saturating_add(items.saturating_mul(complexity(&f.selection)))
+    })
+}
+
+fn is_list(name: &str) -> bool {
+    match name {
+        "competitors" | "competitions" | "events" | "records" | "rankings" => true,
+        _                                                                   => false,
+    }
+}
+
+// Parses the query, checks the limits and resolves it against the data.
+pub fn execute(data: &WCA, query: &str) -> Result<Json, String> {
+    let fields = try!(parse(query));
+    if depth(&fields) > MAX_DEPTH {
+        return Err(format!("Query is nested deeper than {} levels", MAX_DEPTH));
+    }
+    let cost = complexity(&fields);
+    if cost > MAX_COMPLEXITY {
+        return Err(format!("Query has a complexity of {} which is more than the allowed {}", cost, MAX_COMPLEXITY));
+    }
+    object(&fields, "Query", |f| resolve_query(data, f))
+}
+
+fn object<F>(fields: &Vec<Field>, type_name: &str, resolve: F) -> Result<Json, String> where F: Fn(&Field) -> Option<Result<Json, String>> {
+    if fields.is_empty() {
+        return Err(format!("A selection on \"{}\" is required", type_name));
+    }
+    let mut result = BTreeMap::new();
+    for field in fields.iter() {
+        match resolve(field) {
+            Some(value) => { result.insert(field.key(), try!(value)); },
+            None        => { return Err(format!("Cannot query field \"{}\" on type \"{}\"", field.name, type_name)); },
+        }
+    }
+    Ok(Json::Object(result))
+}
+
+fn list<T, F>(items: Vec<T>, resolve: F) -> Result<Json, String> where F: Fn(T) -> Result<Json, String> {
+    let mut result = vec![];
+    for item in items.into_iter() {
+        result.push(try!(resolve(item)));
+    }
+    Ok(Json::Array(result))
+}
+
+fn scalar(field: &Field, value: Json) -> Option<Result<Json, String>> {
+    if field.selection.is_empty() {
+        Some(Ok(value))
+    } else {
+        Some(Err(format!("Field \"{}\" must not have a selection", field.name)))
+    }
+}
+
+fn resolve_query(data: &WCA, field: &Field) -> Option<Result<Json, String>> {
+    let resolved = match field.name.as_slice() {
+        "competitor" => field.string_argument("id").and_then(|id| {
+            match data.find_competitor(&id) {
+                Some(c) => competitor(data, c, &field.selection),
+                None    => Ok(Json::Null),
+            }
+        }),
+        "competitors" => field.string_argument("query").and_then(|q| {
+            let first = try!(field.first());
+            list(data.find_competitors(&q).into_iter().take(first).collect(), |c| competitor(data, c, &field.selection))
+        }),
+        "event" => field.string_argument("id").and_then(|id| {
+            match data.find_events().iter().find(|e| e.id == id) {
+                Some(e) => event(data, e, &field.selection),
+                None    => Ok(Json::Null),
+            }
+        }),
+        "events" => field.first().and_then(|first| {
+            list(data.find_events().iter().take(first).collect(), |e| event(data, e, &field.selection))
+        }),
+        "rankings" => field.string_argument("event").and_then(|puzzle_id| rankings(data, &puzzle_id, field)),
+        "competition" => field.string_argument("id").and_then(|id| {
+            match data.find_competition(&id) {
+                Some(c) => competition(data, &c.id, Some(c), &field.selection),
+                None    => Ok(Json::Null),
+            }
+        }),
+        _ => { return None; },
+    };
+    Some(resolved)
+}
+
+fn competitor(data: &WCA, c: &wca_data::Competitor, fields: &Vec<Field>) -> Result<Json, String> {
+    object(fields, "Competitor", |f| {
+        match f.name.as_slice() {
+            "id"               => scalar(f, Json::String(c.id.clone())),
+            "name"             => scalar(f, Json::String(c.name.clone())),
+            "country"          => scalar(f, Json::String(c.country.clone())),
+            "competitionCount" => scalar(f, Json::U64(c.competition_count as u64)),
+            "gender"           => scalar(f, match c.gender {
+                wca_data::Gender::Male    => Json::String("m".to_string()),
+                wca_data::Gender::Female  => Json::String("f".to_string()),
+                wca_data::Gender::Unknown => Json::Null,
+            }),
+            "records" => Some(f.first().and_then(|first| {
+                match data.find_records(&c.id) {
+                    Some(records) => {
+                        let records: Vec<(&wca_data::Event, &wca_data::Record)> = data.find_events().iter()
+                            .filter_map(|e| records.get(&e.id).map(|r| (e, r)))
+                            .take(first)
+                            .collect();
+                        list(records, |(e, r)| record(data, e, r, &f.selection))
+                    },
+                    None => Ok(Json::Array(vec![])),
+                }
+            })),
+            "competitions" => Some(f.first().and_then(|first| {
+                let competitions = data.find_competitions_of(&c.id).unwrap_or(vec![]);
+                list(competitions.into_iter().take(first).collect(), |a| competition(data, a.competition_id, a.competition, &f.selection))
+            })),
+            "rank" => Some(f.string_argument("event").and_then(|puzzle_id| {
+                let result_type = try!(f.result_type());
+                match data.find_rank_position(&c.id, &puzzle_id, result_type) {
+                    Some(position) => ranking(data, position.ranking, &f.selection),
+                    None           => Ok(Json::Null),
+                }
+            })),
+            _ => None,
+        }
+    })
+}
+
+fn record(data: &WCA, e: &wca_data::Event, r: &wca_data::Record, fields: &Vec<Field>) -> Result<Json, String> {
+    object(fields, "Record", |f| {
+        match f.name.as_slice() {
+            "event"   => Some(event(data, e, &f.selection)),
+            "single"  => scalar(f, Json::U64(r.single.time as u64)),
+            "average" => scalar(f, r.average.map(|a| Json::U64(a.time as u64)).unwrap_or(Json::Null)),
+            _         => None,
+        }
+    })
+}
+
+fn rankings(data: &WCA, puzzle_id: &String, field: &Field) -> Result<Json, String> {
+    let result_type = try!(field.result_type());
+    let first = try!(field.first());
+    match data.find_rankings(puzzle_id, result_type) {
+        Some(rankings) => list(rankings.iter().take(first).collect(), |r| ranking(data, r, &field.selection)),
+        None           => Ok(Json::Array(vec![])),
+    }
+}
+
+fn ranking(data: &WCA, r: &wca_data::Ranking, fields: &Vec<Field>) -> Result<Json, String> {
+    object(fields, "Ranking", |f| {
+        match f.name.as_slice() {
+            "rank"       => scalar(f, Json::U64(r.rank as u64)),
+            "time"       => scalar(f, Json::U64(r.result.time as u64)),
+            "competitor" => Some(match data.find_competitor(&r.competitor_id) {
+                Some(c) => competitor(data, c, &f.selection),
+                None    => Ok(Json::Null),
+            }),
+            _ => None,
+        }
+    })
+}
+
+fn event(data: &WCA, e: &wca_data::Event, fields: &Vec<Field>) -> Result<Json, String> {
+    object(fields, "Event", |f| {
+        match f.name.as_slice() {
+            "id"       => scalar(f, Json::String(e.id.clone())),
+            "name"     => scalar(f, Json::String(e.name.clone())),
+            "rankings" => Some(rankings(data, &e.id, f)),
+            _          => None,
+        }
+    })
+}
+
+fn competition(data: &WCA, id: &String, details: Option<&wca_data::Competition>, fields: &Vec<Field>) -> Result<Json, String> {
+    let detail = |value: Option<String>| value.map(|v| Json::String(v)).unwrap_or(Json::Null);
+    object(fields, "Competition", |f| {
+        match f.name.as_slice() {
+            "id"      => scalar(f, Json::String(id.clone())),
+            "name"    => scalar(f, detail(details.map(|c| c.name.clone()))),
+            "city"    => scalar(f, detail(details.map(|c| c.city.clone()))),
+            "country" => scalar(f, detail(details.map(|c| c.country.clone()))),
+            "date"    => scalar(f, detail(details.map(|c| c.date()))),
+            "competitors" => Some(f.first().and_then(|first| {
+                let entries = data.find_competitors_at(id).unwrap_or(vec![]);
+                let competitors: Vec<&wca_data::Competitor> = entries.iter()
+                    .filter_map(|e| data.find_competitor(e.competitor_id))
+                    .take(first)
+                    .collect();
+                list(competitors, |c| competitor(data, c, &f.selection))
+            })),
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::u64;
+    use fixture_data;
+    use super::{parse, depth, complexity, execute, Field, Value};
+
+    #[test]
+    fn parse_nested_fields() {
+        let fields = parse("{ competitor(id: \"2003BRUC01\") { name records { single } } }").unwrap();
+
+        assert_eq!(fields.len(), 1);
+        assert_eq!(fields[0].name, "competitor".to_string());
+        assert_eq!(fields[0].arguments, vec![("id".to_string(), Value::Str("2003BRUC01".to_string()))]);
+        assert_eq!(fields[0].selection.len(), 2);
+        assert_eq!(fields[0].selection[1].selection[0].name, "single".to_string());
+    }
+
+    #[test]
+    fn parse_named_query_with_alias() {
+        let fields = parse("query Top { best: rankings(event: \"333\", type: average, first: 3) { time } }").unwrap();
+
+        assert_eq!(fields[0].alias, Some("best".to_string()));
+        assert_eq!(fields[0].name, "rankings".to_string());
+        assert_eq!(fields[0].arguments[1], ("type".to_string(), Value::Enum("average".to_string())));
+        assert_eq!(fields[0].arguments[2], ("first".to_string(), Value::Int(3)));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(parse("{ competitor(id: \"2003BRUC01\") { name }").is_err());
+        assert!(parse("mutation { competitor }").is_err());
+        assert!(parse("{ }").is_err());
+    }
+
+    #[test]
+    fn query_limits() {
+        let fields = parse("{ rankings(event: \"333\", first: 10) { competitor { competitions(first: 5) { id } } } }").unwrap();
+        assert_eq!(depth(&fields), 4);
+        // rankings + 10 * (competitor + competitions + 5 * id)
+        assert_eq!(complexity(&fields), 1 + 10 * (1 + 1 + 5 * 1));
+
+        let empty: Vec<Field> = vec![];
+        assert_eq!(depth(&empty), 0);
+    }
+
+    #[test]
+    fn parse_stops_at_the_depth_limit() {
+        let nested: String = (0..32000).map(|_| "{a").collect();
+        assert!(parse(nested.as_slice()).unwrap_err().contains("deeper than 6"));
+    }
+
+    #[test]
+    fn complexity_saturates() {
+        let leaves: String = (0..20000).map(|_| "a ").collect();
+        let query = format!("{{ rankings(first: 1000) {{ competitions(first: 1000) {{ competitors(first: 1000) {{ competitions(first: 1000) {{ competitors(first: 1000) {{ {} }} }} }} }} }} }}", leaves);
+        assert_eq!(complexity(&parse(query.as_slice()).unwrap()), u64::MAX);
+    }
+
+    #[test]
+    fn execute_competitor_with_records() {
+        let data = fixture_data();
+        let result = execute(&data, "{ competitor(id: \"2003BRUC01\") { name records(first: 2) { event { id } single average } } }").unwrap();
+
+        let competitor = result.find("competitor").unwrap();
+        assert_eq!(competitor.find("name").and_then(|n| n.as_string()), Some("Ron van Bruchem"));
+        let records = competitor.find("records").and_then(|r| r.as_array()).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].find_path(&["event", "id"]).and_then(|id| id.as_string()), Some("333"));
+        assert_eq!(records[0].find("single").and_then(|s| s.as_u64()), Some(871));
+        assert_eq!(records[0].find("average").and_then(|a| a.as_u64()), Some(1262));
+    }
+
+    #[test]
+    fn execute_rankings_with_first() {
+        let data = fixture_data();
+        let result = execute(&data, "{ top: rankings(event: \"333\", first: 2) { rank time competitor { id } } }").unwrap();
+
+        let rankings = result.find("top").and_then(|r| r.as_array()).unwrap();
+        assert_eq!(rankings.len(), 2);
+        assert_eq!(rankings[0].find("rank").and_then(|r| r.as_u64()), Some(1));
+        assert_eq!(rankings[0].find("time").and_then(|t| t.as_u64()), Some(708));
+        assert_eq!(rankings[0].find_path(&["competitor", "id"]).and_then(|id| id.as_string()), Some("2005AKKE01"));
+        assert_eq!(rankings[1].find("time").and_then(|t| t.as_u64()), Some(871));
+    }
+
+    #[test]
+    fn execute_rejects_queries_over_the_limits() {
+        let data = fixture_data();
+        let too_deep = execute(&data, "{ competitor(id: \"2003BRUC01\") { competitions { competitors { competitions { competitors { competitions { id } } } } } } }");
+        assert!(too_deep.unwrap_err().contains("deeper than 6"));
+
+        let too_complex = execute(&data, "{ rankings(event: \"333\", first: 1000) { competitor { competitions(first: 1000) { id } } } }");
+        assert!(too_complex.unwrap_err().contains("complexity of 1002001"));
+    }
+}
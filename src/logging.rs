@@ -0,0 +1,250 @@
+use std::ascii::AsciiExt;
+use std::collections::BTreeMap;
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rustc_serialize::json::{Json, ToJson};
+use time;
+
+use iron::{Request, Response, IronResult, BeforeMiddleware, AfterMiddleware};
+use iron::typemap;
+
+use cache::header_value;
+
+pub const REQUEST_ID_HEADER: &'static str = "X-Request-Id";
+// Longer incoming request IDs are replaced by a generated one.
+const MAX_REQUEST_ID_LENGTH: usize = 64;
+
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    /// None for `off`, an error for unknown levels so a typo doesn't turn
+    /// logging off.
+    pub fn from_str(s: &str) -> Result<Option<LogLevel>, String> {
+        match s.to_ascii_lowercase().as_slice() {
+            "off"   => Ok(None),
+            "error" => Ok(Some(LogLevel::Error)),
+            "warn"  => Ok(Some(LogLevel::Warn)),
+            "info"  => Ok(Some(LogLevel::Info)),
+            "debug" => Ok(Some(LogLevel::Debug)),
+            _       => Err(format!("Unknown log level \"{}\", use off, error, warn, info or debug", s)),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            LogLevel::Error => "error",
+            LogLevel::Warn  => "warn",
+            LogLevel::Info  => "info",
+            LogLevel::Debug => "debug",
+        }
+    }
+
+    // Server errors are errors, client errors warnings, everything else info.
+    fn of_status(status: u16) -> LogLevel {
+        if status >= 500 {
+            LogLevel::Error
+        } else if status >= 400 {
+            LogLevel::Warn
+        } else {
+            LogLevel::Info
+        }
+    }
+}
+
+struct RequestStart;
+
+// When the request came in, its ID and the configured level.
+impl typemap::Key for RequestStart { type Value = (u64, String, Option<LogLevel>); }
+
+/// Remembers when a request came in and which ID it has. The ID is taken
+/// from the `X-Request-Id` header, so IDs of a proxy carry over, or generated.
+pub struct AccessLogStart {
+    prefix: String,
+    counter: AtomicUsize,
+    level: Option<LogLevel>,
+}
+
+/// Echoes the request ID and writes one JSON line per request to stdout.
+pub struct AccessLogEnd;
+
+/// The end has to be linked as the last after middleware to see the final
+/// status and body.
+pub fn access_log(level: Option<LogLevel>) -> (AccessLogStart, AccessLogEnd) {
+    let start = AccessLogStart { prefix: format!("{:x}", time::precise_time_ns()), counter: AtomicUsize::new(0), level: level };
+    (start, AccessLogEnd)
+}
+
+fn valid_request_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= MAX_REQUEST_ID_LENGTH && id.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+impl BeforeMiddleware for AccessLogStart {
+    fn before(&self, req: &mut Request) -> IronResult<()> {
+        let id = match header_value(req, REQUEST_ID_HEADER) {
+            Some(ref id) if valid_request_id(id.as_slice()) => id.clone(),
+            _ => format!("{}-{:x}", self.prefix, self.counter.fetch_add(1, Ordering::Relaxed)),
+        };
+        req.extensions.insert::<RequestStart>((time::precise_time_ns(), id, self.level));
+        Ok(())
+    }
+}
+
+pub fn request_id(req: &Request) -> Option<String> {
+    req.extensions.get::<RequestStart>().map(|&(_, ref id, _)| id.clone())
+}
+
+/// Logs an error which is not tied to a response status, unless logging is
+/// turned off.
+pub fn log_error(req: &Request, message: String) {
+    if let Some(&(_, _, None)) = req.extensions.get::<RequestStart>() {
+        return;
+    }
+    let mut d = BTreeMap::new();
+    d.insert("level".to_string(), Json::String(LogLevel::Error.name().to_string()));
+    d.insert("request_id".to_string(), request_id(req).to_json());
+    d.insert("message".to_string(), Json::String(message));
+    println!("{}", Json::Object(d));
+}
+
+/// The log line of a request, `bytes` and `latency_ms` are added by
+/// `complete` once the body has been sent.
+pub fn entry(level: LogLevel, request_id: &str, method: &str, path: &str, status: u16) -> BTreeMap<String, Json> {
+    let mut d = BTreeMap::new();
+    d.insert("level".to_string(), Json::String(level.name().to_string()));
+    d.insert("request_id".to_string(), Json::String(request_id.to_string()));
+    d.insert("method".to_string(), Json::String(method.to_string()));
+    d.insert("path".to_string(), Json::String(path.to_string()));
+    d.insert("status".to_string(), Json::U64(status as u64));
+    d
+}
+
+/// Adds the size of the sent body and the time it took to the line.
+pub fn complete(mut line: BTreeMap<String, Json>, bytes: u64, latency_ns: u64) -> Json {
+    line.insert("bytes".to_string(), Json::U64(bytes));
+    line.insert("latency_ms".to_string(), Json::F64(latency_ns as f64 / 1000000.0));
+    Json::Object(line)
+}
+
+// Passes the body through to hyper and writes the log line when it has been
+// read completely or dropped, so streamed and compressed bodies are counted
+// with the size actually sent and the time it took to encode them.
+struct LoggedBody {
+    body: Box<Read + Send>,
+    line: Option<BTreeMap<String, Json>>,
+    started: u64,
+    bytes: u64,
+}
+
+impl LoggedBody {
+    fn finish(&mut self) {
+        if let Some(line) = self.line.take() {
+            println!("{}", complete(line, self.bytes, time::precise_time_ns() - self.started));
+        }
+    }
+}
+
+impl Read for LoggedBody {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = try!(self.body.read(buf));
+        self.bytes += n as u64;
+        if n == 0 && !buf.is_empty() {
+            self.finish();
+        }
+        Ok(n)
+    }
+}
+
+impl Drop for LoggedBody {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+impl AfterMiddleware for AccessLogEnd {
+    fn after(&self, req: &mut Request, res: Response) -> IronResult<Response> {
+        let mut response = res;
+        let (started, id, max) = match req.extensions.get::<RequestStart>() {
+            Some(&(started, ref id, max)) => (started, id.clone(), max),
+            None                          => { return Ok(response); },
+        };
+        response.headers.set_raw(REQUEST_ID_HEADER, vec![id.clone().into_bytes()]);
+
+        let status = response.status.map(|s| s.to_u16()).unwrap_or(200);
+        let level = LogLevel::of_status(status);
+        match max {
+            Some(max) if level <= max => {
+                let path = format!("/{}", req.url.path.connect("/"));
+                let mut line = entry(level, id.as_slice(), req.method.to_string().as_slice(), path.as_slice(), status);
+                if max == LogLevel::Debug {
+                    line.insert("query".to_string(), req.url.query.to_json());
+                    line.insert("user_agent".to_string(), header_value(req, "User-Agent").to_json());
+                }
+                response.body = match response.body.take() {
+                    Some(body) => Some(Box::new(LoggedBody { body: body, line: Some(line), started: started, bytes: 0 })),
+                    None => {
+                        println!("{}", complete(line, 0, time::precise_time_ns() - started));
+                        None
+                    },
+                };
+            },
+            _ => { },
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Cursor, Read};
+    use super::{LogLevel, LoggedBody, entry, complete, valid_request_id};
+
+    #[test]
+    fn parses_levels() {
+        assert_eq!(LogLevel::from_str("WARN"), Ok(Some(LogLevel::Warn)));
+        assert_eq!(LogLevel::from_str("off"), Ok(None));
+        assert!(LogLevel::from_str("inof").is_err());
+        assert!(LogLevel::Error < LogLevel::Info);
+    }
+
+    #[test]
+    fn levels_by_status() {
+        assert_eq!(LogLevel::of_status(200), LogLevel::Info);
+        assert_eq!(LogLevel::of_status(304), LogLevel::Info);
+        assert_eq!(LogLevel::of_status(404), LogLevel::Warn);
+        assert_eq!(LogLevel::of_status(500), LogLevel::Error);
+    }
+
+    #[test]
+    fn rejects_odd_request_ids() {
+        assert!(valid_request_id("9f86d081-884c"));
+        assert!(!valid_request_id(""));
+        assert!(!valid_request_id("a\"b"));
+        assert!(!valid_request_id((0..65).map(|_| "a").collect::<String>().as_slice()));
+    }
+
+    #[test]
+    fn entry_is_one_json_object() {
+        let line = complete(entry(LogLevel::Info, "abc-1", "GET", "/events", 200), 512, 1500000);
+        assert_eq!(line.find("request_id").and_then(|v| v.as_string()), Some("abc-1"));
+        assert_eq!(line.find("status").and_then(|v| v.as_u64()), Some(200));
+        assert_eq!(line.find("bytes").and_then(|v| v.as_u64()), Some(512));
+        assert_eq!(line.find("latency_ms").and_then(|v| v.as_f64()), Some(1.5));
+        assert!(!line.to_string().contains("\n"));
+    }
+
+    #[test]
+    fn counts_the_bytes_read() {
+        let body: Box<Read + Send> = Box::new(Cursor::new(b"0123456789".to_vec()));
+        let mut logged = LoggedBody { body: body, line: None, started: 0, bytes: 0 };
+        let mut read = String::new();
+        logged.read_to_string(&mut read).unwrap();
+        assert_eq!((read.len(), logged.bytes), (10, 10));
+    }
+}
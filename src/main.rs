@@ -2,11 +2,18 @@ extern crate "wca-data" as w;
 extern crate "rustc-serialize" as rustc_serialize;
 extern crate iron;
 extern crate router;
+extern crate url;
+extern crate flate2;
+extern crate brotli2;
+extern crate time;
 
+use std::env;
 use std::sync::Arc;
+use std::io::Read;
 
 use w::wca_data;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
+use rustc_serialize::Encodable;
 use rustc_serialize::json;
 use rustc_serialize::json::{Json, ToJson};
 use std::path::Path;
@@ -16,6 +23,29 @@ use iron::status;
 use iron::headers;
 use iron::mime::Mime;
 use router::{Router};
+use url::form_urlencoded;
+
+use cache::{header_value, ConditionalGetMiddleware};
+use compression::CompressionMiddleware;
+use cors::CorsMiddleware;
+use error::{ApiError, CatchPanic, ErrorMiddleware};
+use logging::{access_log, LogLevel};
+use ndjson::RankingLines;
+use openapi::OpenApiHandler;
+use routes::Routes;
+use table::{Format, Table};
+
+mod cache;
+mod compression;
+mod cors;
+mod error;
+mod graphql;
+mod logging;
+mod ndjson;
+mod openapi;
+mod routes;
+mod table;
+mod v1;
 
 
 struct CompetitorHandler {
@@ -26,6 +56,10 @@ struct CompetitorSearchHandler {
     data: Arc<wca_data::WCA>,
 }
 
+struct CompetitorBatchHandler {
+    data: Arc<wca_data::WCA>,
+}
+
 struct CompetitorRecordsHandler {
     data: Arc<wca_data::WCA>,
 }
@@ -42,6 +76,89 @@ struct SelectiveRecordsHandler {
     data: Arc<wca_data::WCA>,
 }
 
+struct CompetitorRanksHandler {
+    data: Arc<wca_data::WCA>,
+}
+
+struct CompareHandler {
+    data: Arc<wca_data::WCA>,
+}
+
+struct SumOfRanksHandler {
+    data: Arc<wca_data::WCA>,
+    envelope: bool,
+}
+
+struct KinchHandler {
+    data: Arc<wca_data::WCA>,
+    envelope: bool,
+}
+
+struct ProgressionHandler {
+    data: Arc<wca_data::WCA>,
+    envelope: bool,
+}
+
+struct RecordHistoryHandler {
+    data: Arc<wca_data::WCA>,
+}
+
+struct CompetitorMedalsHandler {
+    data: Arc<wca_data::WCA>,
+}
+
+struct MedalsHandler {
+    data: Arc<wca_data::WCA>,
+    envelope: bool,
+}
+
+struct CompetitionResultsHandler {
+    data: Arc<wca_data::WCA>,
+    envelope: bool,
+}
+
+struct CompetitorCompetitionsHandler {
+    data: Arc<wca_data::WCA>,
+}
+
+struct CompetitionCompetitorsHandler {
+    data: Arc<wca_data::WCA>,
+}
+
+struct ConnectionHandler {
+    data: Arc<wca_data::WCA>,
+}
+
+struct CoCompetitorsHandler {
+    data: Arc<wca_data::WCA>,
+}
+
+struct StatsHandler {
+    data: Arc<wca_data::WCA>,
+}
+
+struct CountriesHandler {
+    data: Arc<wca_data::WCA>,
+}
+
+struct CountryStatsHandler {
+    data: Arc<wca_data::WCA>,
+}
+
+struct GraphQLHandler {
+    data: Arc<wca_data::WCA>,
+}
+
+struct CountryRecordsHandler {
+    data: Arc<wca_data::WCA>,
+    envelope: bool,
+}
+
+struct ContinentRecordsHandler {
+    data: Arc<wca_data::WCA>,
+    envelope: bool,
+}
+
 struct Competitor {
     id: String,
     name: String,
@@ -54,17 +171,240 @@ struct Competitor {
 struct CompetitorPartOfCollection<'a> {
     id: &'a str,
     name: &'a str,
-    gender: &'a str,
+    gender: Option<&'a str>,
     country: &'a str,
     competition_count: u32,
 }
 
+#[derive(RustcEncodable)]
+struct CompetitorBatch<'a> {
+    competitors: Vec<CompetitorPartOfCollection<'a>>,
+    not_found: Vec<&'a str>,
+}
+
 #[derive(RustcEncodable)]
 struct Ranking<'a> {
     time: u32,
     competitor: CompetitorPartOfCollection<'a>,
 }
 
+#[derive(RustcEncodable)]
+struct NeighborRanking<'a> {
+    rank: u32,
+    time: u32,
+    competitor: CompetitorPartOfCollection<'a>,
+}
+
+#[derive(RustcEncodable)]
+struct RankPosition<'a> {
+    rank: u32,
+    time: u32,
+    total: usize,
+    above: Option<NeighborRanking<'a>>,
+    below: Option<NeighborRanking<'a>>,
+}
+
+#[derive(RustcEncodable)]
+struct CompetitorRanks<'a> {
+    competitor_id: &'a str,
+    puzzle_id: &'a str,
+    single: Option<RankPosition<'a>>,
+    average: Option<RankPosition<'a>>,
+}
+
+#[derive(RustcEncodable)]
+struct SumOfRanks<'a> {
+    rank: u32,
+    sum: u32,
+    competitor: CompetitorPartOfCollection<'a>,
+    events: &'a Vec<wca_data::EventRank>,
+}
+
+#[derive(RustcEncodable)]
+struct KinchScore<'a> {
+    rank: u32,
+    score: f64,
+    competitor: CompetitorPartOfCollection<'a>,
+    events: &'a Vec<wca_data::KinchEventScore>,
+}
+
+#[derive(RustcEncodable)]
+struct PersonalBest<'a> {
+    competition_id: &'a str,
+    competition_name: Option<&'a str>,
+    date: Option<String>,
+    round_id: &'a str,
+    single: Option<i32>,
+    average: Option<i32>,
+}
+
+#[derive(RustcEncodable)]
+struct HistoricRecord<'a> {
+    time: i32,
+    region: &'a str,
+    competitor_id: &'a str,
+    competitor_name: &'a str,
+    competition_id: &'a str,
+    competition_name: Option<&'a str>,
+    date: Option<String>,
+    round_id: &'a str,
+    days_stood: Option<i64>,
+}
+
+#[derive(RustcEncodable)]
+struct RecordHistory<'a> {
+    single: Vec<HistoricRecord<'a>>,
+    average: Vec<HistoricRecord<'a>>,
+}
+
+#[derive(RustcEncodable)]
+struct RegionalRecord<'a> {
+    time: u32,
+    holders: Vec<CompetitorPartOfCollection<'a>>,
+}
+
+#[derive(RustcEncodable)]
+struct CurrentRecords<'a> {
+    puzzle_id: &'a str,
+    single: Option<RegionalRecord<'a>>,
+    average: Option<RegionalRecord<'a>>,
+}
+
+#[derive(RustcEncodable)]
+struct Podium<'a> {
+    competition_id: &'a str,
+    competition_name: Option<&'a str>,
+    date: Option<String>,
+    puzzle_id: &'a str,
+    round_id: &'a str,
+    pos: u32,
+    best: i32,
+    average: i32,
+}
+
+#[derive(RustcEncodable)]
+struct CompetitorMedals<'a> {
+    total: wca_data::Medals,
+    events: &'a BTreeMap<&'a String, wca_data::Medals>,
+    podiums: Vec<Podium<'a>>,
+}
+
+#[derive(RustcEncodable)]
+struct CountryMedals<'a> {
+    country: &'a str,
+    medals: wca_data::Medals,
+}
+
+#[derive(RustcEncodable)]
+struct CompetitorMedalCount<'a> {
+    competitor: CompetitorPartOfCollection<'a>,
+    medals: wca_data::Medals,
+}
+
+#[derive(RustcEncodable)]
+struct AttendedCompetition<'a> {
+    id: &'a str,
+    name: Option<&'a str>,
+    date: Option<String>,
+    country: Option<&'a str>,
+    events: Vec<&'a str>,
+}
+
+#[derive(RustcEncodable)]
+struct CompetitionEntry<'a> {
+    competitor: CompetitorPartOfCollection<'a>,
+    events: Vec<&'a str>,
+}
+
+#[derive(RustcEncodable)]
+struct Link<'a> {
+    from: &'a str,
+    to: &'a str,
+    competition_id: &'a str,
+    competition_name: Option<&'a str>,
+}
+
+#[derive(RustcEncodable)]
+struct Connection<'a> {
+    degrees: usize,
+    links: Vec<Link<'a>>,
+}
+
+#[derive(RustcEncodable)]
+struct CoCompetitor<'a> {
+    competitor: CompetitorPartOfCollection<'a>,
+    shared_competitions: u32,
+}
+
+#[derive(RustcEncodable)]
+struct CountryPartOfCollection<'a> {
+    id: &'a str,
+    name: &'a str,
+    continent_id: &'a str,
+    competitors: u32,
+    competitions: u32,
+}
+
+#[derive(RustcEncodable)]
+struct RoundResult<'a> {
+    pos: u32,
+    competitor_id: &'a str,
+    competitor_name: &'a str,
+    country: &'a str,
+    best: i32,
+    average: i32,
+    attempts: Vec<i32>,
+    regional_single_record: &'a Option<String>,
+    regional_average_record: &'a Option<String>,
+}
+
+#[derive(RustcEncodable)]
+struct Round<'a> {
+    round_id: &'a str,
+    results: Vec<RoundResult<'a>>,
+}
+
+#[derive(RustcEncodable)]
+struct EventResults<'a> {
+    puzzle_id: &'a str,
+    rounds: Vec<Round<'a>>,
+}
+
+impl<'a> EventResults<'a> {
+    fn from_event_results(event: &wca_data::EventResults<'a>) -> EventResults<'a> {
+        EventResults {
+            puzzle_id: event.puzzle_id.as_slice(),
+            rounds: event.rounds.iter().map(|round| Round {
+                round_id: round.round_id.as_slice(),
+                results: round.results.iter().map(|r| RoundResult {
+                    pos: r.pos,
+                    competitor_id: r.competitor_id.as_slice(),
+                    competitor_name: r.competitor_name.as_slice(),
+                    country: r.country.as_slice(),
+                    best: r.best,
+                    average: r.average,
+                    // unused attempts are stored as 0
+                    attempts: r.values.iter().map(|v| *v).take_while(|v| *v != 0).collect(),
+                    regional_single_record: &r.regional_single_record,
+                    regional_average_record: &r.regional_average_record,
+                }).collect(),
+            }).collect(),
+        }
+    }
+}
+
+impl<'a> CompetitorPartOfCollection<'a> {
+    fn from_competitor(competitor: &'a wca_data::Competitor) -> CompetitorPartOfCollection<'a> {
+        CompetitorPartOfCollection {
+            id: competitor.id.as_slice(),
+            name: competitor.name.as_slice(),
+            gender: gender_to_str(&competitor.gender),
+            country: competitor.country.as_slice(),
+            competition_count: competitor.competition_count,
+        }
+    }
+}
+
 impl ToJson for Competitor {
     fn to_json(&self) -> Json {
         let mut sub = BTreeMap::new();
@@ -85,11 +425,156 @@ impl ToJson for Competitor {
     }
 }
 
-fn gender_to_str(gender: &wca_data::Gender) -> &str {
+// None for an unknown gender, which is encoded as null.
+fn gender_to_str(gender: &wca_data::Gender) -> Option<&'static str> {
     match gender {
-        &wca_data::Gender::Male   => "m",
-        &wca_data::Gender::Female => "f",
-        _                 => "",
+        &wca_data::Gender::Male   => Some("m"),
+        &wca_data::Gender::Female => Some("f"),
+        _                         => None,
+    }
+}
+
+fn query_values(req: &Request, key: &str) -> Vec<String> {
+    match req.url.query {
+        Some(ref query) => {
+            form_urlencoded::parse(query.as_bytes()).into_iter()
+                .filter(|&(ref k, _)| k.as_slice() == key)
+                .map(|(_, v)| v)
+                .collect()
+        },
+        None => vec![],
+    }
+}
+
+// `?country=` takes precedence over `?continent=`, without either the whole world is used.
+fn scope_from_query(req: &Request) -> wca_data::Scope {
+    match (query_values(req, "country").pop(), query_values(req, "continent").pop()) {
+        (Some(country), _)      => wca_data::Scope::Country(country),
+        (None, Some(continent)) => wca_data::Scope::Continent(continent),
+        (None, None)            => wca_data::Scope::World,
+    }
+}
+
+// `?format=` takes precedence over the Accept header, in which the known
+// media type with the highest q-value wins. None for an unknown `?format=`.
+fn format_from_request(req: &Request) -> Option<Format> {
+    if let Some(format) = query_values(req, "format").pop() {
+        return Format::from_str(format.as_slice());
+    }
+    Some(header_value(req, "Accept").and_then(|accept| Format::negotiate(accept.as_slice())).unwrap_or(Format::Json))
+}
+
+fn unknown_format() -> Response {
+    Response::with(ApiError::bad_request("unknown format").with_details(Json::Array(vec![
+        Json::String("json".to_string()), Json::String("csv".to_string()), Json::String("tsv".to_string())])))
+}
+
+fn competitors_table(competitors: &Vec<CompetitorPartOfCollection>) -> Table {
+    let mut table = Table::new(vec!["id", "name", "country", "gender", "competition_count"]);
+    for c in competitors.iter() {
+        table.push(vec![c.id.to_string(), c.name.to_string(), c.country.to_string(), c.gender.unwrap_or("").to_string(), c.competition_count.to_string()]);
+    }
+    table
+}
+
+// Legacy routes respond with bare arrays, their /v1 versions wrap them as `{"<key>": [..]}`.
+fn encode_collection<T: Encodable>(items: &Vec<T>, key: &str, envelope: bool) -> String {
+    if !envelope {
+        return json::encode(items).unwrap();
+    }
+    let mut wrapped: BTreeMap<String, &Vec<T>> = BTreeMap::new();
+    wrapped.insert(key.to_string(), items);
+    json::encode(&wrapped).unwrap()
+}
+
+fn records_table(records: &Vec<wca_data::RecordWithCompetitor>) -> Table {
+    let mut table = Table::new(vec!["competitor_id", "single", "average"]);
+    for r in records.iter() {
+        table.push(vec![r.competitor_id.clone(), r.single.time.to_string(), r.average.as_ref().map(|a| a.time.to_string()).unwrap_or(String::new())]);
+    }
+    table
+}
+
+// Tied competitors share a rank.
+fn rankings_table(data: &wca_data::WCA, rankings: &Vec<wca_data::Ranking>) -> Table {
+    let mut table = Table::new(vec!["rank", "time", "competitor_id", "name", "country", "gender"]);
+    for r in rankings.iter() {
+        let c = CompetitorPartOfCollection::from_competitor(data.find_competitor(&r.competitor_id).unwrap());
+        table.push(vec![r.rank.to_string(), r.result.time.to_string(), c.id.to_string(), c.name.to_string(), c.country.to_string(), c.gender.unwrap_or("").to_string()]);
+    }
+    table
+}
+
+// Uses the `?events=` parameters or every event which has rankings of the given type.
+fn puzzle_ids_from_query(req: &Request, data: &wca_data::WCA, result_type: wca_data::ResultType) -> Vec<wca_data::PuzzleId> {
+    let puzzle_ids = query_values(req, "events");
+    if !puzzle_ids.is_empty() {
+        return puzzle_ids;
+    }
+    data.find_events().iter()
+        .filter(|e| data.find_rankings(&e.id, result_type).is_some())
+        .map(|e| e.id.clone())
+        .collect()
+}
+
+// Seconds clients and caches may reuse a response without revalidating it.
+const CACHE_MAX_AGE: u32 = 3600;
+
+// Most IDs looked up in one batch request.
+const MAX_BATCH_SIZE: usize = 1000;
+
+// Largest request body read, enough for a full batch or a long query.
+const MAX_BODY_BYTES: u64 = 64 * 1024;
+
+// Smaller responses are not worth compressing.
+const COMPRESSION_MIN_SIZE: u64 = 1024;
+
+const DEFAULT_PER_PAGE: usize = 25;
+const MAX_PER_PAGE: usize = 100;
+
+// Reads `?page=` (starting at 1) and `?per_page=` and returns the range of
+// items to respond with.
+fn page_range(req: &Request, total: usize) -> (usize, usize, usize, usize) {
+    let page = query_values(req, "page").pop().and_then(|p| p.parse().ok()).unwrap_or(1);
+    let page = if page < 1 { 1 } else { page };
+    let per_page = query_values(req, "per_page").pop().and_then(|p| p.parse().ok()).unwrap_or(DEFAULT_PER_PAGE);
+    let per_page = if per_page < 1 || per_page > MAX_PER_PAGE { DEFAULT_PER_PAGE } else { per_page };
+
+    let (start, end) = page_bounds(page, per_page, total);
+    (page, per_page, start, end)
+}
+
+// Pages past the end, including ones too far to compute, are empty.
+fn page_bounds(page: usize, per_page: usize, total: usize) -> (usize, usize) {
+    let start = match (page - 1).checked_mul(per_page) {
+        Some(start) if start < total => start,
+        _                            => total,
+    };
+    let end = if start.saturating_add(per_page) < total { start + per_page } else { total };
+    (start, end)
+}
+
+fn set_pagination_headers(response: &mut Response, page: usize, per_page: usize, total: usize) {
+    response.headers.set_raw("X-Total-Count", vec![total.to_string().into_bytes()]);
+    response.headers.set_raw("X-Page", vec![page.to_string().into_bytes()]);
+    response.headers.set_raw("X-Per-Page", vec![per_page.to_string().into_bytes()]);
+}
+
+// Reads at most MAX_BODY_BYTES, longer bodies are answered with a 413.
+fn read_body(req: &mut Request) -> Result<String, Response> {
+    let mut body = String::new();
+    match req.body.by_ref().take(MAX_BODY_BYTES + 1).read_to_string(&mut body) {
+        Ok(length) if length as u64 > MAX_BODY_BYTES => Err(Response::with(ApiError::payload_too_large(MAX_BODY_BYTES))),
+        Ok(_)                                        => Ok(body),
+        Err(_)                                       => Err(Response::with(ApiError::bad_request("body is not valid UTF-8"))),
+    }
+}
+
+fn result_type_from_str(s: &str) -> Option<wca_data::ResultType> {
+    match s {
+        "single"  => Some(wca_data::ResultType::Single),
+        "average" => Some(wca_data::ResultType::Average),
+        _         => None,
     }
 }
 
@@ -103,20 +588,61 @@ impl Handler for CompetitorHandler {
                 Ok(Response::with((status::Ok, data)))
             },
             None => {
-                Ok(Response::with((status::NotFound, "{\"error\": \"not found\"}")))
+                Ok(Response::with(ApiError::not_found("competitor")))
             },
         }
     }
 }
 
+// Answers `{"competitors": [..], "not_found": [..]}` in the order of the
+// given IDs, duplicates are looked up once.
+fn competitor_batch_response(data: &wca_data::WCA, ids: Vec<String>, format: Format) -> Response {
+    if ids.len() > MAX_BATCH_SIZE {
+        return Response::with(ApiError::bad_request("too many ids").with_details(Json::U64(MAX_BATCH_SIZE as u64)));
+    }
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut unique_ids: Vec<String> = vec![];
+    for id in ids.into_iter() {
+        if seen.insert(id.clone()) {
+            unique_ids.push(id);
+        }
+    }
+
+    let mut batch = CompetitorBatch { competitors: vec![], not_found: vec![] };
+    for (id, competitor) in unique_ids.iter().zip(data.find_competitors_by_ids(&unique_ids).into_iter()) {
+        match competitor {
+            Some(c) => batch.competitors.push(CompetitorPartOfCollection::from_competitor(c)),
+            None    => batch.not_found.push(id.as_slice()),
+        }
+    }
+    if format != Format::Json {
+        return competitors_table(&batch.competitors).to_response(format);
+    }
+    Response::with((status::Ok, json::encode(&batch).unwrap()))
+}
+
+// `?ids=` looks up competitors by ID, `?q=` searches them.
 impl Handler for CompetitorSearchHandler {
     fn handle(&self, req: &mut Request) -> IronResult<Response> {
-        // TODO use a proper way to parse query strings
-        let query = req.url.query.clone().unwrap();
-        let q = &query[2..query.len()];
+        let format = match format_from_request(req) {
+            Some(format) => format,
+            None         => { return Ok(unknown_format()); },
+        };
+
+        let ids = query_values(req, "ids");
+        if !ids.is_empty() {
+            return Ok(competitor_batch_response(&self.data, ids, format));
+        }
+        let q = match query_values(req, "q").pop() {
+            Some(q) => q,
+            None    => { return Ok(Response::with(ApiError::missing_parameter("q"))); },
+        };
 
-        let competitors = self.data.find_competitors(&q.to_string());
-        let competitors: Vec<CompetitorPartOfCollection> = competitors.iter().map(|c| CompetitorPartOfCollection { id: c.id.as_slice(), name: c.name.as_slice(), gender: gender_to_str(&c.gender), country: c.country.as_slice(), competition_count: c.competition_count }).collect();
+        let competitors = self.data.find_competitors(&q);
+        let competitors: Vec<CompetitorPartOfCollection> = competitors.iter().map(|c| CompetitorPartOfCollection::from_competitor(*c)).collect();
+        if format != Format::Json {
+            return Ok(competitors_table(&competitors).to_response(format));
+        }
         let mut wrapped_competitors: BTreeMap<String, &Vec<CompetitorPartOfCollection>> = BTreeMap::new();
         wrapped_competitors.insert("competitors".to_string(), &competitors);
 
@@ -124,6 +650,35 @@ impl Handler for CompetitorSearchHandler {
     }
 }
 
+// For lists too long for a query string, the body is `{"ids": [..]}`.
+impl Handler for CompetitorBatchHandler {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let body = match read_body(req) {
+            Ok(body)      => body,
+            Err(response) => { return Ok(response); },
+        };
+        let body = match Json::from_str(body.as_slice()) {
+            Ok(body) => body,
+            Err(_)   => { return Ok(Response::with(ApiError::bad_request("body is not valid JSON"))); },
+        };
+        let ids = match body.find("ids").and_then(|ids| ids.as_array()) {
+            Some(ids) if !ids.is_empty() => ids,
+            _ => {
+                return Ok(Response::with(ApiError::bad_request("body needs a non-empty `ids` array").with_details(Json::String("ids".to_string()))));
+            },
+        };
+        if ids.iter().any(|id| id.as_string().is_none()) {
+            return Ok(Response::with(ApiError::bad_request("`ids` has to contain strings only").with_details(Json::String("ids".to_string()))));
+        }
+        let ids: Vec<String> = ids.iter().filter_map(|id| id.as_string()).map(|id| id.to_string()).collect();
+        let format = match format_from_request(req) {
+            Some(format) => format,
+            None         => { return Ok(unknown_format()); },
+        };
+        Ok(competitor_batch_response(&self.data, ids, format))
+    }
+}
+
 impl Handler for CompetitorRecordsHandler {
     fn handle(&self, req: &mut Request) -> IronResult<Response> {
 
@@ -134,7 +689,7 @@ impl Handler for CompetitorRecordsHandler {
                 Ok(Response::with((status::Ok, json::encode(r).unwrap())))
             },
             None => {
-                Ok(Response::with((status::NotFound, "{\"error\": \"not found\"}")))
+                Ok(Response::with(ApiError::not_found("competitor")))
             }
         }
 
@@ -143,38 +698,81 @@ impl Handler for CompetitorRecordsHandler {
 
 impl Handler for RecordsHandler {
     fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let streaming = ndjson::requested(req);
+        let format = match format_from_request(req) {
+            Some(format)      => format,
+            None if streaming => Format::Json,
+            None              => { return Ok(unknown_format()); },
+        };
         let ref puzzle = req.extensions.get::<Router>().unwrap().find("puzzle_id").unwrap();
         let ref _type = req.extensions.get::<Router>().unwrap().find("type").unwrap();
-        let rankings = match *_type {
-            "single"  => self.data.find_rankings(&puzzle.to_string(), wca_data::ResultType::Single),
-            "average" => self.data.find_rankings(&puzzle.to_string(), wca_data::ResultType::Average),
-            _         => { return Ok(Response::with((status::NotFound, ""))); }
+        let result_type = match result_type_from_str(*_type) {
+            Some(t) => t,
+            None    => { return Ok(Response::with(ApiError::not_found("result type"))); }
         };
+        let rankings = self.data.find_rankings(&puzzle.to_string(), result_type);
+        if streaming && rankings.is_some() {
+            return Ok(RankingLines::new(self.data.clone(), puzzle.to_string(), result_type).into_response());
+        }
         match rankings {
             Some(v) => {
+                if format != Format::Json {
+                    return Ok(rankings_table(&self.data, v).to_response(format));
+                }
                 let rankings: Vec<Ranking> = v.iter().map(|r| {
                     let competitor = self.data.find_competitor(&r.competitor_id).unwrap();
                     Ranking {
                         time: r.result.time,
-                        competitor: CompetitorPartOfCollection {
-                            id: competitor.id.as_slice(),
-                            name: competitor.name.as_slice(),
-                            gender: gender_to_str(&competitor.gender),
-                            country: competitor.country.as_slice(),
-                            competition_count: competitor.competition_count,
-                        }
+                        competitor: CompetitorPartOfCollection::from_competitor(competitor),
                     }
                 }
                 ).collect();
                 Ok(Response::with((status::Ok, json::encode(&rankings).unwrap())))
             },
             None => {
-                Ok(Response::with((status::NotFound, "")))
+                Ok(Response::with(ApiError::not_found("rankings")))
             },
         }
     }
 }
 
+impl CompetitorRanksHandler {
+    fn neighbor<'a>(&'a self, ranking: Option<&'a wca_data::Ranking>) -> Option<NeighborRanking<'a>> {
+        ranking.map(|r| NeighborRanking {
+            rank: r.rank,
+            time: r.result.time,
+            competitor: CompetitorPartOfCollection::from_competitor(self.data.find_competitor(&r.competitor_id).unwrap()),
+        })
+    }
+
+    fn position<'a>(&'a self, id: &String, puzzle_id: &String, result_type: wca_data::ResultType) -> Option<RankPosition<'a>> {
+        self.data.find_rank_position(id, puzzle_id, result_type).map(|p| RankPosition {
+            rank: p.ranking.rank,
+            time: p.ranking.result.time,
+            total: p.total,
+            above: self.neighbor(p.above),
+            below: self.neighbor(p.below),
+        })
+    }
+}
+
+impl Handler for CompetitorRanksHandler {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let ref id = req.extensions.get::<Router>().unwrap().find("id").unwrap().to_string();
+        let ref puzzle_id = req.extensions.get::<Router>().unwrap().find("puzzle_id").unwrap().to_string();
+
+        let single = self.position(id, puzzle_id, wca_data::ResultType::Single);
+        let average = self.position(id, puzzle_id, wca_data::ResultType::Average);
+
+        if single.is_none() && average.is_none() {
+            return Ok(Response::with(ApiError::not_found("rankings")));
+        }
+
+        let ranks = CompetitorRanks { competitor_id: id.as_slice(), puzzle_id: puzzle_id.as_slice(), single: single, average: average };
+        Ok(Response::with((status::Ok, json::encode(&ranks).unwrap())))
+    }
+}
+
 impl Handler for EventsHandler {
     fn handle(&self, _: &mut Request) -> IronResult<Response> {
         Ok(Response::with((status::Ok, json::encode(self.data.find_events()).unwrap())))
@@ -183,51 +781,615 @@ impl Handler for EventsHandler {
 
 impl Handler for SelectiveRecordsHandler {
     fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let ids = query_values(req, "ids");
+        if ids.is_empty() {
+            return Ok(Response::with(ApiError::missing_parameter("ids")));
+        }
         let ref puzzle_id = req.extensions.get::<Router>().unwrap().find("puzzle_id").unwrap();
-        // parsing of ids= query parameters
-        let foo = req.url.query.clone().unwrap();
-        let ids: Vec<String> = foo.split('&').map(|param| (&param[4..param.len()]).to_string()).collect();
+
+        let format = match format_from_request(req) {
+            Some(format) => format,
+            None         => { return Ok(unknown_format()); },
+        };
 
         let records = self.data.find_rankings_for(&puzzle_id.to_string(), ids);
+        if format != Format::Json {
+            return Ok(records_table(&records).to_response(format));
+        }
         Ok(Response::with((status::Ok, json::encode(&records).unwrap())))
     }
 
 }
 
+impl Handler for CompareHandler {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let ref id1 = req.extensions.get::<Router>().unwrap().find("id1").unwrap();
+        let ref id2 = req.extensions.get::<Router>().unwrap().find("id2").unwrap();
+
+        match self.data.compare(&id1.to_string(), &id2.to_string()) {
+            Some(comparison) => {
+                Ok(Response::with((status::Ok, json::encode(&comparison).unwrap())))
+            },
+            None => {
+                Ok(Response::with(ApiError::not_found("competitor")))
+            }
+        }
+    }
+}
+
+impl Handler for SumOfRanksHandler {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let result_type = match result_type_from_str(req.extensions.get::<Router>().unwrap().find("type").unwrap()) {
+            Some(t) => t,
+            None    => { return Ok(Response::with(ApiError::not_found("result type"))); }
+        };
+        let puzzle_ids = puzzle_ids_from_query(req, &self.data, result_type);
+
+        let sor = self.data.sum_of_ranks(&puzzle_ids, result_type, &scope_from_query(req));
+        let sor: Vec<SumOfRanks> = sor.iter().map(|s| SumOfRanks {
+            rank: s.rank,
+            sum: s.sum,
+            competitor: CompetitorPartOfCollection::from_competitor(self.data.find_competitor(&s.competitor_id).unwrap()),
+            events: &s.events,
+        }).collect();
+        Ok(Response::with((status::Ok, encode_collection(&sor, "ranks", self.envelope))))
+    }
+}
+
+impl Handler for KinchHandler {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let puzzle_ids = puzzle_ids_from_query(req, &self.data, wca_data::ResultType::Single);
+
+        let kinch = self.data.kinch_ranks(&puzzle_ids, &scope_from_query(req));
+        let kinch: Vec<KinchScore> = kinch.iter().map(|k| KinchScore {
+            rank: k.rank,
+            score: k.score,
+            competitor: CompetitorPartOfCollection::from_competitor(self.data.find_competitor(&k.competitor_id).unwrap()),
+            events: &k.events,
+        }).collect();
+        Ok(Response::with((status::Ok, encode_collection(&kinch, "ranks", self.envelope))))
+    }
+}
+
+impl Handler for ProgressionHandler {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let ref id = req.extensions.get::<Router>().unwrap().find("id").unwrap();
+        let ref puzzle_id = req.extensions.get::<Router>().unwrap().find("puzzle_id").unwrap();
+
+        match self.data.find_progression(&id.to_string(), &puzzle_id.to_string()) {
+            Some(progression) => {
+                let progression: Vec<PersonalBest> = progression.iter().map(|pb| {
+                    let competition = self.data.find_competition(&pb.result.competition_id);
+                    PersonalBest {
+                        competition_id: pb.result.competition_id.as_slice(),
+                        competition_name: competition.map(|c| c.name.as_slice()),
+                        date: competition.map(|c| c.date()),
+                        round_id: pb.result.round_id.as_slice(),
+                        single: pb.single,
+                        average: pb.average,
+                    }
+                }).collect();
+                Ok(Response::with((status::Ok, encode_collection(&progression, "progression", self.envelope))))
+            },
+            None => {
+                Ok(Response::with(ApiError::not_found("progression")))
+            }
+        }
+    }
+}
+
+impl RecordHistoryHandler {
+    fn history<'a>(&'a self, records: &'a Vec<wca_data::HistoricRecord<'a>>, region: &Option<String>) -> Vec<HistoricRecord<'a>> {
+        records.iter().filter(|r| region.as_ref().map(|id| *id == r.region).unwrap_or(true)).map(|r| {
+            let competition = self.data.find_competition(&r.result.competition_id);
+            HistoricRecord {
+                time: r.time,
+                region: r.region.as_slice(),
+                competitor_id: r.result.competitor_id.as_slice(),
+                competitor_name: r.result.competitor_name.as_slice(),
+                competition_id: r.result.competition_id.as_slice(),
+                competition_name: competition.map(|c| c.name.as_slice()),
+                date: competition.map(|c| c.date()),
+                round_id: r.result.round_id.as_slice(),
+                days_stood: r.days_stood,
+            }
+        }).collect()
+    }
+}
+
+impl Handler for RecordHistoryHandler {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let puzzle_id = req.extensions.get::<Router>().unwrap().find("puzzle_id").unwrap().to_string();
+        if !self.data.find_events().iter().any(|e| e.id == puzzle_id) {
+            return Ok(Response::with(ApiError::not_found("event")));
+        }
+        let (level, region) = match query_values(req, "scope").pop() {
+            None                                 => (wca_data::RecordLevel::World, None),
+            Some(ref s) if *s == "world"         => (wca_data::RecordLevel::World, None),
+            Some(ref s) if *s == "continent"     => (wca_data::RecordLevel::Continent, query_values(req, "continent").pop()),
+            Some(ref s) if *s == "country"       => (wca_data::RecordLevel::Country, query_values(req, "country").pop()),
+            Some(_) => {
+                return Ok(Response::with(ApiError::bad_request("unknown scope")));
+            },
+        };
+
+        let single = self.data.find_record_history(&puzzle_id, wca_data::ResultType::Single, level);
+        let average = self.data.find_record_history(&puzzle_id, wca_data::ResultType::Average, level);
+        let history = RecordHistory { single: self.history(&single, &region), average: self.history(&average, &region) };
+        Ok(Response::with((status::Ok, json::encode(&history).unwrap())))
+    }
+}
+
+impl Handler for CompetitorMedalsHandler {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let ref id = req.extensions.get::<Router>().unwrap().find("id").unwrap();
+
+        match self.data.find_medals(&id.to_string()) {
+            Some(medals) => {
+                let podiums = medals.podiums.iter().map(|r| {
+                    let competition = self.data.find_competition(&r.competition_id);
+                    Podium {
+                        competition_id: r.competition_id.as_slice(),
+                        competition_name: competition.map(|c| c.name.as_slice()),
+                        date: competition.map(|c| c.date()),
+                        puzzle_id: r.puzzle_id.as_slice(),
+                        round_id: r.round_id.as_slice(),
+                        pos: r.pos,
+                        best: r.best,
+                        average: r.average,
+                    }
+                }).collect();
+                let medals = CompetitorMedals { total: medals.total, events: &medals.events, podiums: podiums };
+                Ok(Response::with((status::Ok, json::encode(&medals).unwrap())))
+            },
+            None => {
+                Ok(Response::with(ApiError::not_found("competitor")))
+            }
+        }
+    }
+}
+
+// Without `?country=` the medal table of all countries is returned, otherwise
+// the medal table of the competitors of that country.
+impl Handler for MedalsHandler {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        match query_values(req, "country").pop() {
+            Some(country_id) => {
+                if self.data.find_country(&country_id).is_none() {
+                    return Ok(Response::with(ApiError::not_found("country")));
+                }
+                let table = self.data.medal_table_of_country(&country_id);
+                let table: Vec<CompetitorMedalCount> = table.iter().filter_map(|&(id, medals)|
+                    self.data.find_competitor(id).map(|c| CompetitorMedalCount { competitor: CompetitorPartOfCollection::from_competitor(c), medals: medals })
+                ).collect();
+                Ok(Response::with((status::Ok, encode_collection(&table, "medals", self.envelope))))
+            },
+            None => {
+                let table = self.data.medal_table();
+                let table: Vec<CountryMedals> = table.iter().map(|&(country, medals)| CountryMedals { country: country.as_slice(), medals: medals }).collect();
+                Ok(Response::with((status::Ok, encode_collection(&table, "medals", self.envelope))))
+            },
+        }
+    }
+}
+
+// Serves both `/competitions/:id/results` and `/competitions/:id/results/:event_id`.
+impl Handler for CompetitionResultsHandler {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let id = req.extensions.get::<Router>().unwrap().find("id").unwrap().to_string();
+        let puzzle_id = req.extensions.get::<Router>().unwrap().find("event_id").map(|e| e.to_string());
+
+        let events = match self.data.find_competition_results(&id) {
+            Some(events) => events,
+            None         => { return Ok(Response::with(ApiError::not_found("competition"))); },
+        };
+        match puzzle_id {
+            Some(puzzle_id) => {
+                match events.iter().find(|e| *e.puzzle_id == puzzle_id) {
+                    Some(event) => Ok(Response::with((status::Ok, json::encode(&EventResults::from_event_results(event)).unwrap()))),
+                    None        => Ok(Response::with(ApiError::not_found("event"))),
+                }
+            },
+            None => {
+                let events: Vec<EventResults> = events.iter().map(|e| EventResults::from_event_results(e)).collect();
+                Ok(Response::with((status::Ok, encode_collection(&events, "events", self.envelope))))
+            },
+        }
+    }
+}
+
+impl Handler for CompetitorCompetitionsHandler {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let ref id = req.extensions.get::<Router>().unwrap().find("id").unwrap();
+
+        match self.data.find_competitions_of(&id.to_string()) {
+            Some(competitions) => {
+                let (page, per_page, start, end) = page_range(req, competitions.len());
+                let page_of_competitions: Vec<AttendedCompetition> = competitions[start..end].iter().map(|c| AttendedCompetition {
+                    id: c.competition_id.as_slice(),
+                    name: c.competition.map(|c| c.name.as_slice()),
+                    date: c.competition.map(|c| c.date()),
+                    country: c.competition.map(|c| c.country.as_slice()),
+                    events: c.puzzle_ids.iter().map(|e| e.as_slice()).collect(),
+                }).collect();
+                let mut wrapped_competitions: BTreeMap<String, &Vec<AttendedCompetition>> = BTreeMap::new();
+                wrapped_competitions.insert("competitions".to_string(), &page_of_competitions);
+
+                let mut response = Response::with((status::Ok, json::encode(&wrapped_competitions).unwrap()));
+                set_pagination_headers(&mut response, page, per_page, competitions.len());
+                Ok(response)
+            },
+            None => {
+                Ok(Response::with(ApiError::not_found("competitor")))
+            }
+        }
+    }
+}
+
+impl Handler for CompetitionCompetitorsHandler {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let ref id = req.extensions.get::<Router>().unwrap().find("id").unwrap();
+
+        match self.data.find_competitors_at(&id.to_string()) {
+            Some(entries) => {
+                let entries: Vec<CompetitionEntry> = entries.iter().filter_map(|e|
+                    self.data.find_competitor(e.competitor_id).map(|c| CompetitionEntry {
+                        competitor: CompetitorPartOfCollection::from_competitor(c),
+                        events: e.puzzle_ids.iter().map(|p| p.as_slice()).collect(),
+                    })
+                ).collect();
+                let mut wrapped_competitors: BTreeMap<String, &Vec<CompetitionEntry>> = BTreeMap::new();
+                wrapped_competitors.insert("competitors".to_string(), &entries);
+                Ok(Response::with((status::Ok, json::encode(&wrapped_competitors).unwrap())))
+            },
+            None => {
+                Ok(Response::with(ApiError::not_found("competition")))
+            }
+        }
+    }
+}
+
+impl Handler for ConnectionHandler {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let id1 = req.extensions.get::<Router>().unwrap().find("id1").unwrap().to_string();
+        let id2 = req.extensions.get::<Router>().unwrap().find("id2").unwrap().to_string();
+
+        if self.data.find_competitor(&id1).is_none() || self.data.find_competitor(&id2).is_none() {
+            return Ok(Response::with(ApiError::not_found("competitor")));
+        }
+        match self.data.find_connection(&id1, &id2) {
+            Some(links) => {
+                let connection = Connection {
+                    degrees: links.len(),
+                    links: links.iter().map(|l| Link {
+                        from: l.from.as_slice(),
+                        to: l.to.as_slice(),
+                        competition_id: l.competition_id.as_slice(),
+                        competition_name: self.data.find_competition(l.competition_id).map(|c| c.name.as_slice()),
+                    }).collect(),
+                };
+                Ok(Response::with((status::Ok, json::encode(&connection).unwrap())))
+            },
+            None => {
+                Ok(Response::with(ApiError::new(status::NotFound, "not_connected", "competitors are not connected".to_string())))
+            }
+        }
+    }
+}
+
+impl Handler for CoCompetitorsHandler {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let ref id = req.extensions.get::<Router>().unwrap().find("id").unwrap();
+
+        match self.data.find_co_competitors(&id.to_string()) {
+            Some(co_competitors) => {
+                let (page, per_page, start, end) = page_range(req, co_competitors.len());
+                let page_of_co_competitors: Vec<CoCompetitor> = co_competitors[start..end].iter().filter_map(|&(id, count)|
+                    self.data.find_competitor(id).map(|c| CoCompetitor { competitor: CompetitorPartOfCollection::from_competitor(c), shared_competitions: count })
+                ).collect();
+                let mut wrapped_competitors: BTreeMap<String, &Vec<CoCompetitor>> = BTreeMap::new();
+                wrapped_competitors.insert("competitors".to_string(), &page_of_co_competitors);
+
+                let mut response = Response::with((status::Ok, json::encode(&wrapped_competitors).unwrap()));
+                set_pagination_headers(&mut response, page, per_page, co_competitors.len());
+                Ok(response)
+            },
+            None => {
+                Ok(Response::with(ApiError::not_found("competitor")))
+            }
+        }
+    }
+}
+
+const DEFAULT_BUCKETS: usize = 20;
+
+impl Handler for StatsHandler {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let puzzle_id = req.extensions.get::<Router>().unwrap().find("puzzle_id").unwrap().to_string();
+        let result_type = match result_type_from_str(req.extensions.get::<Router>().unwrap().find("type").unwrap()) {
+            Some(t) => t,
+            None    => { return Ok(Response::with(ApiError::not_found("result type"))); }
+        };
+        let gender = match query_values(req, "gender").pop() {
+            Some(ref g) if *g == "m" => Some(wca_data::Gender::Male),
+            Some(ref g) if *g == "f" => Some(wca_data::Gender::Female),
+            Some(_)                  => { return Ok(Response::with(ApiError::bad_request("unknown gender, use m or f"))); },
+            None                     => None,
+        };
+        // Bounded, as the histogram is allocated up front.
+        let buckets = match query_values(req, "buckets").pop().map(|b| b.parse::<usize>()) {
+            None => DEFAULT_BUCKETS,
+            Some(Ok(buckets)) if buckets >= 1 && buckets <= wca_data::MAX_BUCKETS => buckets,
+            Some(_) => {
+                return Ok(Response::with(ApiError::bad_request(format!("buckets must be between 1 and {}", wca_data::MAX_BUCKETS).as_slice()).with_details(Json::U64(wca_data::MAX_BUCKETS as u64))));
+            },
+        };
+
+        match self.data.distribution(&puzzle_id, result_type, gender, &scope_from_query(req), buckets) {
+            Some(distribution) => {
+                Ok(Response::with((status::Ok, json::encode(&distribution).unwrap())))
+            },
+            None => {
+                Ok(Response::with(ApiError::not_found("rankings")))
+            }
+        }
+    }
+}
+
+// `?sort=` is one of `name` (default), `competitors` or `competitions`,
+// `?order=` is `asc` or `desc`. Counts are sorted descending by default.
+impl Handler for CountriesHandler {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let mut countries: Vec<CountryPartOfCollection> = self.data.find_countries().iter().map(|c| {
+            let stats = self.data.find_country_stats(&c.id).unwrap();
+            CountryPartOfCollection {
+                id: c.id.as_slice(),
+                name: c.name.as_slice(),
+                continent_id: c.continent_id.as_slice(),
+                competitors: stats.competitors,
+                competitions: stats.competitions,
+            }
+        }).collect();
+
+        let sort = query_values(req, "sort").pop().unwrap_or("name".to_string());
+        let descending = match query_values(req, "order").pop() {
+            Some(ref o) if *o == "asc"  => false,
+            Some(ref o) if *o == "desc" => true,
+            _                           => sort != "name",
+        };
+        match sort.as_slice() {
+            "name"         => countries.sort_by(|a, b| a.name.cmp(b.name)),
+            "competitors"  => countries.sort_by(|a, b| a.competitors.cmp(&b.competitors)),
+            "competitions" => countries.sort_by(|a, b| a.competitions.cmp(&b.competitions)),
+            _              => {
+                return Ok(Response::with(ApiError::bad_request("unknown sort")));
+            },
+        }
+        if descending {
+            countries.reverse();
+        }
+
+        let mut wrapped_countries: BTreeMap<String, &Vec<CountryPartOfCollection>> = BTreeMap::new();
+        wrapped_countries.insert("countries".to_string(), &countries);
+        Ok(Response::with((status::Ok, json::encode(&wrapped_countries).unwrap())))
+    }
+}
+
+impl Handler for CountryStatsHandler {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let ref id = req.extensions.get::<Router>().unwrap().find("id").unwrap();
+
+        match self.data.find_country_stats(&id.to_string()) {
+            Some(stats) => {
+                Ok(Response::with((status::Ok, json::encode(stats).unwrap())))
+            },
+            None => {
+                Ok(Response::with(ApiError::not_found("country")))
+            }
+        }
+    }
+}
+
+fn graphql_error(message: String) -> Response {
+    let mut error = BTreeMap::new();
+    error.insert("message".to_string(), Json::String(message));
+    let mut errors = BTreeMap::new();
+    errors.insert("errors".to_string(), Json::Array(vec![Json::Object(error)]));
+    Response::with((status::BadRequest, Json::Object(errors).to_string()))
+}
+
+// Accepts the query as `?query=` or as POST body, either plain or as JSON
+// object with a `query` member.
+impl Handler for GraphQLHandler {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let query = match query_values(req, "query").pop() {
+            Some(query) => query,
+            None        => match read_body(req) {
+                Ok(body) => match Json::from_str(body.as_slice()) {
+                    Ok(json) => json.find("query").and_then(|q| q.as_string()).map(|q| q.to_string()).unwrap_or(body),
+                    Err(_)   => body,
+                },
+                Err(response) => { return Ok(response); },
+            },
+        };
+        if query.trim().is_empty() {
+            return Ok(graphql_error("No query given".to_string()));
+        }
+
+        match graphql::execute(&self.data, query.as_slice()) {
+            Ok(data) => {
+                let mut result = BTreeMap::new();
+                result.insert("data".to_string(), data);
+                Ok(Response::with((status::Ok, Json::Object(result).to_string())))
+            },
+            Err(message) => Ok(graphql_error(message)),
+        }
+    }
+}
+
+fn current_records_response(data: &wca_data::WCA, scope: wca_data::Scope, envelope: bool) -> Response {
+    let regional_record = |record: &Option<wca_data::RegionalRecord>| record.as_ref().map(|r| RegionalRecord {
+        time: r.time,
+        holders: r.holders.iter().map(|id| CompetitorPartOfCollection::from_competitor(data.find_competitor(*id).unwrap())).collect(),
+    });
+    let records = data.current_records(&scope);
+    let records: Vec<CurrentRecords> = records.iter().map(|r| CurrentRecords {
+        puzzle_id: r.puzzle_id.as_slice(),
+        single: regional_record(&r.single),
+        average: regional_record(&r.average),
+    }).collect();
+    Response::with((status::Ok, encode_collection(&records, "records", envelope)))
+}
+
+impl Handler for CountryRecordsHandler {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let id = req.extensions.get::<Router>().unwrap().find("id").unwrap().to_string();
+        if self.data.find_country(&id).is_none() {
+            return Ok(Response::with(ApiError::not_found("country")));
+        }
+        Ok(current_records_response(&self.data, wca_data::Scope::Country(id), self.envelope))
+    }
+}
+
+impl Handler for ContinentRecordsHandler {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let id = req.extensions.get::<Router>().unwrap().find("id").unwrap().to_string();
+        if !self.data.find_countries().iter().any(|c| c.continent_id == id) {
+            return Ok(Response::with(ApiError::not_found("continent")));
+        }
+        Ok(current_records_response(&self.data, wca_data::Scope::Continent(id), self.envelope))
+    }
+}
+
 struct JSONAcceptHeaderMiddleware;
 
 impl AfterMiddleware for JSONAcceptHeaderMiddleware {
     fn after(&self, _: &mut Request, res: Response) -> IronResult<Response> {
         let mut response = res;
-        let mime: Mime = "application/json;charset=utf-8".parse().unwrap();
-        response.headers.set(headers::ContentType(mime));
+        if response.headers.get::<headers::ContentType>().is_none() {
+            let mime: Mime = "application/json;charset=utf-8".parse().unwrap();
+            response.headers.set(headers::ContentType(mime));
+        }
         Ok(response)
     }
 }
 
+fn register_routes(routes: &mut Routes, data: Arc<wca_data::WCA>) {
+    // Served under both trees, bare arrays are wrapped under /v1
+    for prefix in ["", "/v1"].iter() {
+        let route = |path: &str| format!("{}{}", prefix, path);
+        let envelope = !prefix.is_empty();
+        routes.get(route("/competitors").as_slice(), CompetitorSearchHandler { data: data.clone() });
+        routes.post(route("/competitors").as_slice(), CompetitorBatchHandler { data: data.clone() });
+        routes.get(route("/competitors/:id/ranks/:puzzle_id").as_slice(), CompetitorRanksHandler { data: data.clone() });
+        routes.get(route("/competitors/:id/progression/:puzzle_id").as_slice(), ProgressionHandler { data: data.clone(), envelope: envelope });
+        routes.get(route("/competitors/:id/competitions").as_slice(), CompetitorCompetitionsHandler { data: data.clone() });
+        routes.get(route("/competitors/:id/co-competitors").as_slice(), CoCompetitorsHandler { data: data.clone() });
+        routes.get(route("/competitors/:id/medals").as_slice(), CompetitorMedalsHandler { data: data.clone() });
+        routes.get(route("/records/:puzzle_id/history").as_slice(), RecordHistoryHandler { data: data.clone() });
+        routes.get(route("/connections/:id1/:id2").as_slice(), ConnectionHandler { data: data.clone() });
+        routes.get(route("/compare/:id1/:id2").as_slice(), CompareHandler { data: data.clone() });
+        routes.get(route("/sor/:type").as_slice(), SumOfRanksHandler { data: data.clone(), envelope: envelope });
+        routes.get(route("/kinch").as_slice(), KinchHandler { data: data.clone(), envelope: envelope });
+        routes.get(route("/competitions/:id/results").as_slice(), CompetitionResultsHandler { data: data.clone(), envelope: envelope });
+        routes.get(route("/competitions/:id/results/:event_id").as_slice(), CompetitionResultsHandler { data: data.clone(), envelope: envelope });
+        routes.get(route("/competitions/:id/competitors").as_slice(), CompetitionCompetitorsHandler { data: data.clone() });
+        routes.get(route("/stats/:puzzle_id/:type").as_slice(), StatsHandler { data: data.clone() });
+        routes.get(route("/medals").as_slice(), MedalsHandler { data: data.clone(), envelope: envelope });
+        routes.get(route("/countries").as_slice(), CountriesHandler { data: data.clone() });
+        routes.get(route("/countries/:id/stats").as_slice(), CountryStatsHandler { data: data.clone() });
+        routes.get(route("/countries/:id/records").as_slice(), CountryRecordsHandler { data: data.clone(), envelope: envelope });
+        routes.get(route("/continents/:id/records").as_slice(), ContinentRecordsHandler { data: data.clone(), envelope: envelope });
+    }
+
+    // Legacy routes, kept unchanged for existing clients
+    routes.get("/competitors/:id", CompetitorHandler { data: data.clone() });
+    routes.get("/competitors/:id/records", CompetitorRecordsHandler { data: data.clone() });
+    routes.get("/records/:puzzle_id/:type", RecordsHandler { data: data.clone() });
+    routes.get("/records/:puzzle_id/", SelectiveRecordsHandler { data: data.clone() });
+    routes.get("/events", EventsHandler { data: data.clone() });
+
+    routes.get("/v1/competitors/:id", v1::CompetitorHandler { data: data.clone() });
+    routes.get("/v1/competitors/:id/records", v1::CompetitorRecordsHandler { data: data.clone() });
+    routes.get("/v1/records/:puzzle_id/:type", v1::RankingsHandler { data: data.clone() });
+    routes.get("/v1/records/:puzzle_id/", v1::SelectiveRecordsHandler { data: data.clone() });
+    routes.get("/v1/events", v1::EventsHandler { data: data.clone() });
+
+    routes.get("/graphql", GraphQLHandler { data: data.clone() });
+    routes.post("/graphql", GraphQLHandler { data: data.clone() });
+
+    // Describes every route above, so it has to come last
+    let document = openapi::document(routes.routes()).to_string();
+    routes.get(openapi::PATH, OpenApiHandler { document: document });
+}
+
 fn main() {
     println!("Importing");
-    let w = wca_data::build_from_files(Path::new("./data/WCA_export_Persons.tsv"),
-                                       Path::new("./data/WCA_export_Results.tsv"),
-                                       Path::new("./data/WCA_export_RanksSingle.tsv"),
-                                       Path::new("./data/WCA_export_RanksAverage.tsv"),
-                                       Path::new("./data/WCA_export_Events.tsv"));
+    let w = wca_data::build_from_files(&wca_data::ExportFiles::in_dir(Path::new("./data")));
     println!("Importing Done");
 
     let w_arc = Arc::new(*w);
 
-    let mut router = Router::new();
+    let mut routes = Routes::new();
+    register_routes(&mut routes, w_arc.clone());
 
-    router.get("/competitors", CompetitorSearchHandler { data: w_arc.clone() });
-    router.get("/competitors/:id", CompetitorHandler { data: w_arc.clone() });
-    router.get("/competitors/:id/records", CompetitorRecordsHandler { data: w_arc.clone() });
-    router.get("/records/:puzzle_id/:type", RecordsHandler { data: w_arc.clone() });
-    router.get("/records/:puzzle_id/", SelectiveRecordsHandler { data: w_arc.clone() });
-    router.get("/events", EventsHandler { data: w_arc.clone() });
-
-    let mut chain = Chain::new(router);
+    let conditional_get = ConditionalGetMiddleware::new(&w_arc, CACHE_MAX_AGE);
+    // off, error, warn, info or debug
+    let level = match LogLevel::from_str(env::var("LOG_LEVEL").unwrap_or("info".to_string()).as_slice()) {
+        Ok(level)    => level,
+        Err(message) => panic!("{}", message),
+    };
+    let (log_start, log_end) = access_log(level);
+    let mut chain = Chain::new(CatchPanic::new(routes.into_router()));
 
+    chain.link_before(log_start);
+    chain.link_after(ErrorMiddleware);
+    chain.link_after(conditional_get);
     chain.link_after(JSONAcceptHeaderMiddleware);
+    chain.link_after(CompressionMiddleware { min_size: COMPRESSION_MIN_SIZE });
+    // Comma separated, e.g. `https://example.org,https://stats.example.org` or `*`
+    chain.link_after(CorsMiddleware::from_list(env::var("CORS_ALLOWED_ORIGINS").unwrap_or(String::new()).as_slice()));
+    chain.link_after(log_end);
 
     Iron::new(chain).http("0.0.0.0:3000").unwrap();
 }
+
+// The data of `tests/fixtures` for the unit tests of every module.
+#[cfg(test)]
+pub fn fixture_data() -> Arc<wca_data::WCA> {
+    Arc::new(*wca_data::build_from_files(&wca_data::ExportFiles::in_dir(Path::new("./tests/fixtures"))))
+}
+
+#[cfg(test)]
+mod test {
+    use routes::Routes;
+    use openapi;
+    use std::usize;
+    use super::{fixture_data, register_routes, page_bounds};
+
+    #[test]
+    fn every_route_is_documented() {
+        let mut routes = Routes::new();
+        register_routes(&mut routes, fixture_data());
+
+        assert_eq!(openapi::undocumented(routes.routes()), vec![]);
+    }
+
+    #[test]
+    fn every_response_has_a_schema() {
+        let mut routes = Routes::new();
+        register_routes(&mut routes, fixture_data());
+
+        let empty: Vec<String> = vec![];
+        assert_eq!(openapi::bare_objects(&openapi::document(routes.routes()), String::new()), empty);
+    }
+
+    #[test]
+    fn pages_past_the_end_are_empty() {
+        assert_eq!(page_bounds(1, 20, 45), (0, 20));
+        assert_eq!(page_bounds(3, 20, 45), (40, 45));
+        assert_eq!(page_bounds(4, 20, 45), (45, 45));
+        assert_eq!(page_bounds(usize::MAX, 100, 45), (45, 45));
+        assert_eq!(page_bounds(usize::MAX / 100 + 2, 100, 45), (45, 45));
+    }
+}
@@ -0,0 +1,108 @@
+use std::cmp;
+use std::io::{self, Read};
+use std::sync::Arc;
+
+use rustc_serialize::json;
+use w::wca_data;
+
+use iron::{Request, Response};
+use iron::status;
+use iron::headers;
+use iron::mime::Mime;
+
+use cache::header_value;
+use super::{query_values, CompetitorPartOfCollection, Ranking};
+
+pub const MIME: &'static str = "application/x-ndjson";
+
+/// `?format=ndjson` or `Accept: application/x-ndjson`
+pub fn requested(req: &Request) -> bool {
+    match query_values(req, "format").pop() {
+        Some(format) => format == "ndjson",
+        None => header_value(req, "Accept").map(|accept|
+            accept.split(',').any(|mime| mime.split(';').next().unwrap().trim() == MIME)
+        ).unwrap_or(false),
+    }
+}
+
+/// A ranking as body with one JSON object per line, each line is encoded
+/// when hyper reads it so the whole ranking never sits in memory as JSON.
+pub struct RankingLines {
+    data: Arc<wca_data::WCA>,
+    puzzle_id: String,
+    result_type: wca_data::ResultType,
+    next: usize,
+    line: Vec<u8>,
+    position: usize,
+}
+
+impl RankingLines {
+    pub fn new(data: Arc<wca_data::WCA>, puzzle_id: String, result_type: wca_data::ResultType) -> RankingLines {
+        RankingLines { data: data, puzzle_id: puzzle_id, result_type: result_type, next: 0, line: vec![], position: 0 }
+    }
+
+    // Encodes the next ranking into `line`, false after the last one.
+    fn fill(&mut self) -> bool {
+        let line = match self.data.find_rankings(&self.puzzle_id, self.result_type).and_then(|rankings| rankings.get(self.next)) {
+            Some(r) => {
+                let ranking = Ranking {
+                    time: r.result.time,
+                    competitor: CompetitorPartOfCollection::from_competitor(self.data.find_competitor(&r.competitor_id).unwrap()),
+                };
+                json::encode(&ranking).unwrap()
+            },
+            None => { return false; },
+        };
+        self.next += 1;
+        self.line = line.into_bytes();
+        self.line.push(b'\n');
+        self.position = 0;
+        true
+    }
+
+    pub fn into_response(self) -> Response {
+        let mut response = Response::with(status::Ok);
+        response.body = Some(Box::new(self));
+        let mime: Mime = MIME.parse().unwrap();
+        response.headers.set(headers::ContentType(mime));
+        response
+    }
+}
+
+impl Read for RankingLines {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.line.len() && !self.fill() {
+            return Ok(0);
+        }
+        let n = cmp::min(buf.len(), self.line.len() - self.position);
+        for (to, from) in buf.iter_mut().zip(self.line[self.position..self.position + n].iter()) {
+            *to = *from;
+        }
+        self.position += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Read;
+    use rustc_serialize::json::Json;
+    use w::wca_data;
+    use fixture_data;
+    use super::RankingLines;
+
+    #[test]
+    fn one_ranking_per_line() {
+        let data = fixture_data();
+        let puzzle_id = "333".to_string();
+        let rankings = data.find_rankings(&puzzle_id, wca_data::ResultType::Single).unwrap();
+
+        let mut body = String::new();
+        RankingLines::new(data.clone(), puzzle_id.clone(), wca_data::ResultType::Single).read_to_string(&mut body).unwrap();
+        let lines: Vec<&str> = body.lines().collect();
+        assert_eq!(lines.len(), rankings.len());
+        let first = Json::from_str(lines[0]).unwrap();
+        assert_eq!(first.find("time").and_then(|t| t.as_u64()), Some(rankings[0].result.time as u64));
+        assert!(body.ends_with("\n"));
+    }
+}
@@ -0,0 +1,454 @@
+use std::ascii::AsciiExt;
+use std::collections::BTreeMap;
+use rustc_serialize::json::Json;
+
+use iron::{Handler, Request, Response, IronResult};
+use iron::status;
+use iron::method::Method;
+
+pub const PATH: &'static str = "/openapi.json";
+
+/// Serves the OpenAPI 3 document built from the registered routes.
+pub struct OpenApiHandler {
+    pub document: String,
+}
+
+impl Handler for OpenApiHandler {
+    fn handle(&self, _: &mut Request) -> IronResult<Response> {
+        Ok(Response::with((status::Ok, self.document.clone())))
+    }
+}
+
+struct Param {
+    name: &'static str,
+    description: &'static str,
+    required: bool,
+    schema: Json,
+}
+
+struct Operation {
+    summary: &'static str,
+    query: Vec<Param>,
+    response: Json,
+    paginated: bool,
+    tabular: bool,
+    streaming: bool,
+}
+
+fn obj(members: Vec<(&str, Json)>) -> Json {
+    let mut d = BTreeMap::new();
+    for (key, value) in members.into_iter() {
+        d.insert(key.to_string(), value);
+    }
+    Json::Object(d)
+}
+
+fn s(value: &str) -> Json {
+    Json::String(value.to_string())
+}
+
+fn string() -> Json {
+    obj(vec![("type", s("string"))])
+}
+
+fn integer() -> Json {
+    obj(vec![("type", s("integer"))])
+}
+
+fn number() -> Json {
+    obj(vec![("type", s("number"))])
+}
+
+fn boolean() -> Json {
+    obj(vec![("type", s("boolean"))])
+}
+
+fn nullable(schema: Json) -> Json {
+    match schema {
+        Json::Object(mut d) => {
+            d.insert("nullable".to_string(), Json::Boolean(true));
+            Json::Object(d)
+        },
+        schema => schema,
+    }
+}
+
+fn array(items: Json) -> Json {
+    obj(vec![("type", s("array")), ("items", items)])
+}
+
+fn reference(name: &str) -> Json {
+    obj(vec![("$ref", s(format!("#/components/schemas/{}", name).as_slice()))])
+}
+
+fn object(properties: Vec<(&str, Json)>) -> Json {
+    obj(vec![("type", s("object")), ("properties", obj(properties))])
+}
+
+// A response with one of several shapes, depending on the parameters.
+fn one_of(schemas: Vec<Json>) -> Json {
+    obj(vec![("oneOf", Json::Array(schemas))])
+}
+
+// An object keyed by e.g. event, with values of the given schema.
+fn map(values: Json) -> Json {
+    obj(vec![("type", s("object")), ("additionalProperties", values)])
+}
+
+// A collection as `{"<name>": [..]}`
+fn wrapped(name: &str, items: Json) -> Json {
+    object(vec![(name, array(items))])
+}
+
+// Bare array in the legacy tree, wrapped in the /v1 one.
+fn collection(v1: bool, name: &str, items: Json) -> Json {
+    if v1 { wrapped(name, items) } else { array(items) }
+}
+
+fn param(name: &'static str, description: &'static str, required: bool, schema: Json) -> Param {
+    Param { name: name, description: description, required: required, schema: schema }
+}
+
+fn scope_params() -> Vec<Param> {
+    vec![param("country", "Restricts to a country, takes precedence over continent", false, string()),
+         param("continent", "Restricts to a continent", false, string())]
+}
+
+fn format_param() -> Param {
+    param("format", "json, csv or tsv, overrides the Accept header", false, string())
+}
+
+fn op(summary: &'static str, query: Vec<Param>, response: Json) -> Operation {
+    Operation { summary: summary, query: query, response: response, paginated: false, tabular: false, streaming: false }
+}
+
+fn paginated(mut operation: Operation) -> Operation {
+    operation.query.push(param("page", "Page starting at 1", false, integer()));
+    operation.query.push(param("per_page", "Items per page, at most 100", false, integer()));
+    operation.paginated = true;
+    operation
+}
+
+fn tabular(mut operation: Operation) -> Operation {
+    operation.query.push(format_param());
+    operation.tabular = true;
+    operation
+}
+
+// Also available as one JSON object per line with `?format=ndjson`.
+fn streaming(mut operation: Operation) -> Operation {
+    for p in operation.query.iter_mut().filter(|p| p.name == "format") {
+        p.description = "json, csv, tsv or ndjson, overrides the Accept header";
+    }
+    operation.streaming = true;
+    operation
+}
+
+// Routes served in the legacy and the /v1 tree, by their path without the
+// /v1 prefix.
+fn shared_operation(method: &Method, glob: &str, v1: bool) -> Option<Operation> {
+    if *method == Method::Post && glob == "/competitors" {
+        return Some(tabular(op("Looks up the competitors given as body `{\"ids\": [..]}`", vec![], reference("CompetitorBatch"))));
+    }
+    if *method != Method::Get {
+        return None;
+    }
+    let operation = match glob {
+        "/competitors" => tabular(op("Searches competitors by name or WCA ID, or looks them up by `ids`",
+                                     vec![param("q", "Part of a name or WCA ID, required without ids", false, string()),
+                                          param("ids", "WCA IDs to look up, the response is a CompetitorBatch then", false, array(string()))],
+                                     one_of(vec![wrapped("competitors", reference("CompetitorPartOfCollection")), reference("CompetitorBatch")]))),
+        "/competitors/:id/ranks/:puzzle_id" => op("Rank of a competitor in an event with the neighbouring ranks", vec![], reference("CompetitorRanks")),
+        "/competitors/:id/progression/:puzzle_id" => op("Personal bests of a competitor over time", vec![], collection(v1, "progression", reference("PersonalBest"))),
+        "/competitors/:id/competitions" => paginated(op("Competitions a competitor attended", vec![], wrapped("competitions", reference("AttendedCompetition")))),
+        "/competitors/:id/co-competitors" => paginated(op("Competitors who shared a competition with a competitor", vec![], wrapped("competitors", reference("CoCompetitor")))),
+        "/competitors/:id/medals" => op("Medals of a competitor", vec![], reference("CompetitorMedals")),
+        "/records/:puzzle_id/history" => op("History of records in an event",
+                                            vec![param("scope", "world, continent or country", false, string()),
+                                                 param("continent", "Continent for scope=continent", false, string()),
+                                                 param("country", "Country for scope=country", false, string())],
+                                            reference("RecordHistory")),
+        "/connections/:id1/:id2" => op("Shortest chain of shared competitions between two competitors", vec![], reference("Connection")),
+        "/compare/:id1/:id2" => op("Head to head comparison of two competitors", vec![], reference("Comparison")),
+        "/sor/:type" => {
+            let mut query = scope_params();
+            query.push(param("events", "Events to rank in, all by default", false, array(string())));
+            op("Sum of ranks", query, collection(v1, "ranks", reference("SumOfRanks")))
+        },
+        "/kinch" => {
+            let mut query = scope_params();
+            query.push(param("events", "Events to score, all by default", false, array(string())));
+            op("Kinch ranks", query, collection(v1, "ranks", reference("KinchScore")))
+        },
+        "/competitions/:id/results" => op("Results of a competition by event and round", vec![], collection(v1, "events", reference("EventResults"))),
+        "/competitions/:id/results/:event_id" => op("Results of one event of a competition", vec![], reference("EventResults")),
+        "/competitions/:id/competitors" => op("Competitors at a competition", vec![], wrapped("competitors", reference("CompetitionEntry"))),
+        "/stats/:puzzle_id/:type" => {
+            let mut query = scope_params();
+            query.push(param("gender", "m or f", false, string()));
+            query.push(param("buckets", "Number of histogram buckets, 1 to 100", false, integer()));
+            op("Distribution of results in an event", query, reference("Distribution"))
+        },
+        "/medals" => op("Medal table by country, or by competitor within ?country=",
+                        vec![param("country", "Ranks the competitors of a country", false, string())],
+                        collection(v1, "medals", one_of(vec![reference("CountryMedals"), reference("CompetitorMedalCount")]))),
+        "/countries" => op("Countries with their statistics",
+                           vec![param("sort", "name, competitors or competitions", false, string()),
+                                param("order", "asc or desc", false, string())],
+                           wrapped("countries", reference("Country"))),
+        "/countries/:id/stats" => op("Statistics of a country", vec![], reference("CountryStats")),
+        "/countries/:id/records" => op("National records", vec![], collection(v1, "records", reference("CurrentRecords"))),
+        "/continents/:id/records" => op("Continental records", vec![], collection(v1, "records", reference("CurrentRecords"))),
+        _ => { return None; },
+    };
+    Some(operation)
+}
+
+fn operation(method: &Method, glob: &str) -> Option<Operation> {
+    let operation = match (method, glob) {
+        (&Method::Get, "/competitors/:id") => op("A competitor", vec![],
+                                                 object(vec![("competitor", reference("CompetitorPartOfCollection"))])),
+        (&Method::Get, "/competitors/:id/records") => op("Personal records of a competitor keyed by event", vec![],
+                                                         obj(vec![("type", s("object")), ("additionalProperties", reference("Record"))])),
+        (&Method::Get, "/records/:puzzle_id/:type") => streaming(tabular(op("Full ranking of an event", vec![], array(reference("Ranking"))))),
+        (&Method::Get, "/records/:puzzle_id/") => tabular(op("Records of the given competitors in an event",
+                                                             vec![param("ids", "WCA IDs", true, array(string()))],
+                                                             array(reference("RecordWithCompetitor")))),
+        (&Method::Get, "/events") => op("Events", vec![], array(reference("Event"))),
+        (&Method::Get, "/v1/competitors/:id") => op("A competitor", vec![], reference("CompetitorPartOfCollection")),
+        (&Method::Get, "/v1/competitors/:id/records") => op("Personal records of a competitor in event order", vec![],
+                                                            wrapped("records", reference("EventRecord"))),
+        (&Method::Get, "/v1/records/:puzzle_id/:type") => paginated(tabular(op("Ranking of an event", vec![], wrapped("rankings", reference("V1Ranking"))))),
+        (&Method::Get, "/v1/records/:puzzle_id/") => tabular(op("Records of the given competitors in an event",
+                                                                vec![param("ids", "WCA IDs", true, array(string()))],
+                                                                wrapped("records", reference("V1Record")))),
+        (&Method::Get, "/v1/events") => op("Events", vec![], wrapped("events", reference("Event"))),
+        (&Method::Get, "/graphql") => op("Runs a GraphQL query",
+                                         vec![param("query", "The GraphQL query", true, string())],
+                                         reference("GraphQLResult")),
+        (&Method::Post, "/graphql") => op("Runs a GraphQL query given as body or as JSON {\"query\": ..}", vec![], reference("GraphQLResult")),
+        (&Method::Get, PATH) => op("This document", vec![], reference("OpenAPI")),
+        _ => {
+            let v1 = glob.starts_with("/v1/");
+            return shared_operation(method, if v1 { &glob[3..] } else { glob }, v1);
+        },
+    };
+    Some(operation)
+}
+
+fn schemas() -> Json {
+    let competitor = object(vec![("id", string()), ("name", string()), ("gender", nullable(string())),
+                                 ("country", string()), ("competition_count", integer())]);
+    let result = object(vec![("time", integer())]);
+    let error = object(vec![("error", object(vec![("code", string()), ("message", string()), ("details", obj(vec![]))]))]);
+    let neighbor = object(vec![("rank", integer()), ("time", integer()), ("competitor", reference("CompetitorPartOfCollection"))]);
+    let rank_position = object(vec![("rank", integer()), ("time", integer()), ("total", integer()),
+                                    ("above", nullable(reference("NeighborRanking"))), ("below", nullable(reference("NeighborRanking")))]);
+    let historic_record = object(vec![("time", integer()), ("region", string()), ("competitor_id", string()), ("competitor_name", string()),
+                                      ("competition_id", string()), ("competition_name", nullable(string())), ("date", nullable(string())),
+                                      ("round_id", string()), ("days_stood", nullable(integer()))]);
+    let podium = object(vec![("competition_id", string()), ("competition_name", nullable(string())), ("date", nullable(string())),
+                             ("puzzle_id", string()), ("round_id", string()), ("pos", integer()), ("best", integer()), ("average", integer())]);
+    let round_result = object(vec![("pos", integer()), ("competitor_id", string()), ("competitor_name", string()), ("country", string()),
+                                   ("best", integer()), ("average", integer()), ("attempts", array(integer())),
+                                   ("regional_single_record", nullable(string())), ("regional_average_record", nullable(string()))]);
+    let result_comparison = object(vec![("first", nullable(reference("Result"))), ("second", nullable(reference("Result"))),
+                                        ("leader", nullable(string())), ("margin", nullable(integer()))]);
+    obj(vec![
+        ("CompetitorPartOfCollection", competitor),
+        ("Result", result),
+        ("Record", object(vec![("single", reference("Result")), ("average", nullable(reference("Result")))])),
+        ("CompetitorBatch", object(vec![("competitors", array(reference("CompetitorPartOfCollection"))), ("not_found", array(string()))])),
+        ("Ranking", object(vec![("time", integer()), ("competitor", reference("CompetitorPartOfCollection"))])),
+        ("RecordWithCompetitor", object(vec![("competitor_id", string()), ("single", reference("Result")), ("average", nullable(reference("Result")))])),
+        ("Event", object(vec![("id", string()), ("name", string())])),
+        ("EventRecord", object(vec![("puzzle_id", string()), ("single", integer()), ("average", nullable(integer()))])),
+        ("V1Ranking", object(vec![("rank", integer()), ("time", integer()), ("competitor", reference("CompetitorPartOfCollection"))])),
+        ("V1Record", object(vec![("competitor_id", string()), ("single", integer()), ("average", nullable(integer()))])),
+        ("NeighborRanking", neighbor),
+        ("RankPosition", rank_position),
+        ("CompetitorRanks", object(vec![("competitor_id", string()), ("puzzle_id", string()),
+                                        ("single", nullable(reference("RankPosition"))), ("average", nullable(reference("RankPosition")))])),
+        ("PersonalBest", object(vec![("competition_id", string()), ("competition_name", nullable(string())), ("date", nullable(string())),
+                                     ("round_id", string()), ("single", nullable(integer())), ("average", nullable(integer()))])),
+        ("AttendedCompetition", object(vec![("id", string()), ("name", nullable(string())), ("date", nullable(string())),
+                                            ("country", nullable(string())), ("events", array(string()))])),
+        ("CoCompetitor", object(vec![("competitor", reference("CompetitorPartOfCollection")), ("shared_competitions", integer())])),
+        ("Medals", object(vec![("gold", integer()), ("silver", integer()), ("bronze", integer())])),
+        ("Podium", podium),
+        ("CompetitorMedals", object(vec![("total", reference("Medals")), ("events", map(reference("Medals"))), ("podiums", array(reference("Podium")))])),
+        ("CountryMedals", object(vec![("country", string()), ("medals", reference("Medals"))])),
+        ("CompetitorMedalCount", object(vec![("competitor", reference("CompetitorPartOfCollection")), ("medals", reference("Medals"))])),
+        ("HistoricRecord", historic_record),
+        ("RecordHistory", object(vec![("single", array(reference("HistoricRecord"))), ("average", array(reference("HistoricRecord")))])),
+        ("Link", object(vec![("from", string()), ("to", string()), ("competition_id", string()), ("competition_name", nullable(string()))])),
+        ("Connection", object(vec![("degrees", integer()), ("links", array(reference("Link")))])),
+        ("ResultComparison", result_comparison),
+        ("EventComparison", object(vec![("puzzle_id", string()), ("single", reference("ResultComparison")), ("average", reference("ResultComparison"))])),
+        ("Comparison", object(vec![("first_id", string()), ("second_id", string()), ("first_score", integer()), ("second_score", integer()),
+                                   ("events", array(reference("EventComparison"))), ("shared_competitions", array(string()))])),
+        ("EventRank", object(vec![("puzzle_id", string()), ("rank", integer()), ("penalty", boolean())])),
+        ("SumOfRanks", object(vec![("rank", integer()), ("sum", integer()), ("competitor", reference("CompetitorPartOfCollection")),
+                                   ("events", array(reference("EventRank")))])),
+        ("KinchEventScore", object(vec![("puzzle_id", string()), ("score", number())])),
+        ("KinchScore", object(vec![("rank", integer()), ("score", number()), ("competitor", reference("CompetitorPartOfCollection")),
+                                   ("events", array(reference("KinchEventScore")))])),
+        ("RoundResult", round_result),
+        ("Round", object(vec![("round_id", string()), ("results", array(reference("RoundResult")))])),
+        ("EventResults", object(vec![("puzzle_id", string()), ("rounds", array(reference("Round")))])),
+        ("CompetitionEntry", object(vec![("competitor", reference("CompetitorPartOfCollection")), ("events", array(string()))])),
+        ("Bucket", object(vec![("from", integer()), ("to", integer()), ("count", integer())])),
+        ("Distribution", object(vec![("count", integer()), ("mean", number()), ("p1", integer()), ("p10", integer()), ("p50", integer()),
+                                     ("p90", integer()), ("histogram", array(reference("Bucket")))])),
+        ("Country", object(vec![("id", string()), ("name", string()), ("continent_id", string()), ("competitors", integer()), ("competitions", integer())])),
+        ("CountryStats", object(vec![("country_id", string()), ("competitors", integer()),
+                                     ("genders", object(vec![("male", integer()), ("female", integer()), ("unknown", integer())])),
+                                     ("competitions", integer()), ("active_competitors", map(integer())), ("events", map(integer()))])),
+        ("RegionalRecord", object(vec![("time", integer()), ("holders", array(reference("CompetitorPartOfCollection")))])),
+        ("CurrentRecords", object(vec![("puzzle_id", string()), ("single", nullable(reference("RegionalRecord"))),
+                                       ("average", nullable(reference("RegionalRecord")))])),
+        // `data` mirrors the selection of the query
+        ("GraphQLResult", object(vec![("data", map(obj(vec![]))), ("errors", array(object(vec![("message", string())])))])),
+        ("OpenAPI", object(vec![("openapi", string()), ("info", object(vec![("title", string()), ("version", string())])),
+                                ("paths", map(obj(vec![]))), ("components", object(vec![("schemas", map(obj(vec![])))]))])),
+        ("Error", error),
+    ])
+}
+
+// `/competitors/:id` becomes `/competitors/{id}`
+fn openapi_path(glob: &str) -> String {
+    let segments: Vec<String> = glob.split('/').map(|segment| {
+        if segment.starts_with(":") { format!("{{{}}}", &segment[1..]) } else { segment.to_string() }
+    }).collect();
+    segments.connect("/")
+}
+
+fn operation_json(glob: &str, operation: Operation) -> Json {
+    let mut parameters: Vec<Json> = glob.split('/').filter(|segment| segment.starts_with(":")).map(|segment| obj(vec![
+        ("name", s(&segment[1..])), ("in", s("path")), ("required", Json::Boolean(true)), ("schema", string())
+    ])).collect();
+    for p in operation.query.into_iter() {
+        parameters.push(obj(vec![
+            ("name", s(p.name)), ("in", s("query")), ("description", s(p.description)),
+            ("required", Json::Boolean(p.required)), ("schema", p.schema),
+        ]));
+    }
+
+    let mut content = vec![("application/json", obj(vec![("schema", operation.response)]))];
+    if operation.tabular {
+        content.push(("text/csv", obj(vec![("schema", string())])));
+        content.push(("text/tab-separated-values", obj(vec![("schema", string())])));
+    }
+    if operation.streaming {
+        content.push(("application/x-ndjson", obj(vec![("schema", string())])));
+    }
+    let mut ok = vec![("description", s("OK")), ("content", obj(content))];
+    if operation.paginated {
+        ok.push(("headers", obj(vec![
+            ("X-Total-Count", obj(vec![("schema", integer())])),
+            ("X-Page", obj(vec![("schema", integer())])),
+            ("X-Per-Page", obj(vec![("schema", integer())])),
+        ])));
+    }
+    let error = obj(vec![("description", s("Error")), ("content", obj(vec![
+        ("application/json", obj(vec![("schema", reference("Error"))])),
+    ]))]);
+
+    obj(vec![
+        ("summary", s(operation.summary)),
+        ("parameters", Json::Array(parameters)),
+        ("responses", obj(vec![("200", obj(ok)), ("default", error)])),
+    ])
+}
+
+/// Routes without an entry in the document, the document itself included.
+pub fn undocumented(routes: &Vec<(String, Vec<Method>)>) -> Vec<(String, Method)> {
+    let mut missing = vec![];
+    for &(ref glob, ref methods) in routes.iter() {
+        for method in methods.iter() {
+            if operation(method, glob.as_slice()).is_none() {
+                missing.push((glob.clone(), method.clone()));
+            }
+        }
+    }
+    missing
+}
+
+/// The OpenAPI 3 document for the given routes plus `/openapi.json` itself.
+pub fn document(routes: &Vec<(String, Vec<Method>)>) -> Json {
+    let mut routes = routes.clone();
+    if !routes.iter().any(|&(ref glob, _)| glob.as_slice() == PATH) {
+        routes.push((PATH.to_string(), vec![Method::Get]));
+    }
+
+    let mut paths = BTreeMap::new();
+    for &(ref glob, ref methods) in routes.iter() {
+        let mut item = BTreeMap::new();
+        for method in methods.iter() {
+            if let Some(operation) = operation(method, glob.as_slice()) {
+                item.insert(method.to_string().to_ascii_lowercase(), operation_json(glob.as_slice(), operation));
+            }
+        }
+        paths.insert(openapi_path(glob.as_slice()), Json::Object(item));
+    }
+
+    obj(vec![
+        ("openapi", s("3.0.0")),
+        ("info", obj(vec![("title", s("WCA API")), ("version", s("1"))])),
+        ("paths", Json::Object(paths)),
+        ("components", obj(vec![("schemas", schemas())])),
+    ])
+}
+
+// Paths of object schemas without members, a generated client can only type
+// them as `any`.
+#[cfg(test)]
+pub fn bare_objects(schema: &Json, path: String) -> Vec<String> {
+    let mut found = vec![];
+    match *schema {
+        Json::Object(ref d) => {
+            if d.get("type").and_then(|t| t.as_string()) == Some("object") && !d.contains_key("properties") && !d.contains_key("additionalProperties") {
+                found.push(path.clone());
+            }
+            for (key, value) in d.iter() {
+                found.extend(bare_objects(value, format!("{}/{}", path, key)).into_iter());
+            }
+        },
+        Json::Array(ref items) => {
+            for item in items.iter() {
+                found.extend(bare_objects(item, path.clone()).into_iter());
+            }
+        },
+        _ => { },
+    }
+    found
+}
+
+#[cfg(test)]
+mod test {
+    use iron::method::Method;
+    use super::{openapi_path, document, undocumented};
+
+    #[test]
+    fn converts_globs_to_paths() {
+        assert_eq!(openapi_path("/competitors/:id/ranks/:puzzle_id"), "/competitors/{id}/ranks/{puzzle_id}");
+        assert_eq!(openapi_path("/records/:puzzle_id/"), "/records/{puzzle_id}/");
+    }
+
+    #[test]
+    fn reports_unknown_routes() {
+        let routes = vec![("/competitors".to_string(), vec![Method::Get, Method::Delete]), ("/nope".to_string(), vec![Method::Get])];
+        assert_eq!(undocumented(&routes), vec![("/competitors".to_string(), Method::Delete), ("/nope".to_string(), Method::Get)]);
+    }
+
+    #[test]
+    fn describes_v1_and_legacy_routes_differently() {
+        let routes = vec![("/events".to_string(), vec![Method::Get]), ("/v1/events".to_string(), vec![Method::Get])];
+        let document = document(&routes);
+        let legacy = document.find_path(&["paths", "/events", "get", "responses", "200", "content", "application/json", "schema", "type"]);
+        let v1 = document.find_path(&["paths", "/v1/events", "get", "responses", "200", "content", "application/json", "schema", "type"]);
+        assert_eq!(legacy.and_then(|t| t.as_string()), Some("array"));
+        assert_eq!(v1.and_then(|t| t.as_string()), Some("object"));
+        assert!(document.find_path(&["paths", "/openapi.json", "get"]).is_some());
+    }
+}
@@ -0,0 +1,54 @@
+use iron::Handler;
+use iron::method::Method;
+use router::Router;
+
+use cors::PreflightHandler;
+use error::MethodNotAllowedHandler;
+
+/// Wraps the router to remember which methods are registered for each route,
+/// so requests with any other method are answered with a 405 and `OPTIONS`
+/// with a CORS preflight response.
+pub struct Routes {
+    router: Router,
+    routes: Vec<(String, Vec<Method>)>,
+}
+
+impl Routes {
+    pub fn new() -> Routes {
+        Routes { router: Router::new(), routes: vec![] }
+    }
+
+    pub fn get<H: Handler>(&mut self, glob: &str, handler: H) {
+        self.route(Method::Get, glob, handler);
+    }
+
+    pub fn post<H: Handler>(&mut self, glob: &str, handler: H) {
+        self.route(Method::Post, glob, handler);
+    }
+
+    pub fn route<H: Handler>(&mut self, method: Method, glob: &str, handler: H) {
+        self.router.route(method.clone(), glob, handler);
+        match self.routes.iter().position(|&(ref g, _)| g.as_slice() == glob) {
+            Some(i) => self.routes[i].1.push(method),
+            None    => self.routes.push((glob.to_string(), vec![method])),
+        }
+    }
+
+    /// The registered routes with their methods, in order of registration.
+    pub fn routes(&self) -> &Vec<(String, Vec<Method>)> {
+        &self.routes
+    }
+
+    pub fn into_router(self) -> Router {
+        let mut router = self.router;
+        for &(ref glob, ref allowed) in self.routes.iter() {
+            for method in [Method::Get, Method::Post, Method::Put, Method::Patch, Method::Delete].iter() {
+                if !allowed.contains(method) {
+                    router.route(method.clone(), glob.as_slice(), MethodNotAllowedHandler { allowed: allowed.clone() });
+                }
+            }
+            router.route(Method::Options, glob.as_slice(), PreflightHandler { allowed: allowed.clone() });
+        }
+        router
+    }
+}
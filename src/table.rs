@@ -0,0 +1,147 @@
+use iron::Response;
+use iron::status;
+use iron::headers;
+use iron::mime::Mime;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Format {
+    Json,
+    Csv,
+    Tsv,
+}
+
+impl Format {
+    pub fn from_str(s: &str) -> Option<Format> {
+        match s {
+            "json" => Some(Format::Json),
+            "csv"  => Some(Format::Csv),
+            "tsv"  => Some(Format::Tsv),
+            _      => None,
+        }
+    }
+
+    pub fn from_mime(mime: &str) -> Option<Format> {
+        match mime.split(';').next().unwrap().trim() {
+            "application/json"          => Some(Format::Json),
+            "text/csv"                  => Some(Format::Csv),
+            "text/tab-separated-values" => Some(Format::Tsv),
+            _                           => None,
+        }
+    }
+
+    /// Picks the known format with the highest q-value from an `Accept`
+    /// header, earlier media types win ties and `q=0` rules one out.
+    pub fn negotiate(accept: &str) -> Option<Format> {
+        let mut best: Option<(Format, f32)> = None;
+        for item in accept.split(',') {
+            let mut parts = item.split(';');
+            let format = match Format::from_mime(parts.next().unwrap()) {
+                Some(format) => format,
+                None         => { continue; },
+            };
+            let q = parts.filter_map(|p| {
+                let p = p.trim();
+                if p.starts_with("q=") { p[2..].parse::<f32>().ok() } else { None }
+            }).next().unwrap_or(1.0);
+            if q > 0.0 && best.map(|(_, best_q)| q > best_q).unwrap_or(true) {
+                best = Some((format, q));
+            }
+        }
+        best.map(|(format, _)| format)
+    }
+
+    fn content_type(&self) -> &'static str {
+        match *self {
+            Format::Json => "application/json;charset=utf-8",
+            Format::Csv  => "text/csv;charset=utf-8",
+            Format::Tsv  => "text/tab-separated-values;charset=utf-8",
+        }
+    }
+}
+
+/// Rows of a tabular endpoint, rendered as CSV or TSV with a header line.
+/// The columns are fixed per endpoint so spreadsheets importing them keep
+/// working.
+pub struct Table {
+    columns: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(columns: Vec<&'static str>) -> Table {
+        Table { columns: columns, rows: vec![] }
+    }
+
+    pub fn push(&mut self, row: Vec<String>) {
+        assert_eq!(row.len(), self.columns.len());
+        self.rows.push(row);
+    }
+
+    pub fn render(&self, format: Format) -> String {
+        let separator = if format == Format::Tsv { "\t" } else { "," };
+        let mut out = String::new();
+        let header: Vec<String> = self.columns.iter().map(|c| escape(c, format)).collect();
+        out.push_str(header.connect(separator).as_slice());
+        out.push_str("\r\n");
+        for row in self.rows.iter() {
+            let fields: Vec<String> = row.iter().map(|f| escape(f.as_slice(), format)).collect();
+            out.push_str(fields.connect(separator).as_slice());
+            out.push_str("\r\n");
+        }
+        out
+    }
+
+    pub fn to_response(&self, format: Format) -> Response {
+        let mut response = Response::with((status::Ok, self.render(format)));
+        let mime: Mime = format.content_type().parse().unwrap();
+        response.headers.set(headers::ContentType(mime));
+        response
+    }
+}
+
+// CSV fields are quoted as in RFC 4180, TSV has no quoting so tabs and line
+// breaks are replaced by spaces.
+fn escape(field: &str, format: Format) -> String {
+    match format {
+        Format::Tsv => field.chars().map(|c| if c == '\t' || c == '\n' || c == '\r' { ' ' } else { c }).collect(),
+        _ => {
+            if field.contains_char(',') || field.contains_char('"') || field.contains_char('\n') || field.contains_char('\r') {
+                format!("\"{}\"", field.replace("\"", "\"\""))
+            } else {
+                field.to_string()
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Format, Table};
+
+    #[test]
+    fn csv_quotes_fields_when_needed() {
+        let mut table = Table::new(vec!["id", "name"]);
+        table.push(vec!["2007HABE01".to_string(), "Habermaas, \"Tim\"".to_string()]);
+        assert_eq!(table.render(Format::Csv), "id,name\r\n2007HABE01,\"Habermaas, \"\"Tim\"\"\"\r\n");
+    }
+
+    #[test]
+    fn tsv_replaces_tabs() {
+        let mut table = Table::new(vec!["id", "name"]);
+        table.push(vec!["2007HABE01".to_string(), "Tim\tHabermaas".to_string()]);
+        assert_eq!(table.render(Format::Tsv), "id\tname\r\n2007HABE01\tTim Habermaas\r\n");
+    }
+
+    #[test]
+    fn format_from_mime_ignores_parameters() {
+        assert_eq!(Format::from_mime("text/csv; charset=utf-8"), Some(Format::Csv));
+        assert_eq!(Format::from_mime("text/html"), None);
+    }
+
+    #[test]
+    fn negotiates_by_q_value() {
+        assert_eq!(Format::negotiate("text/csv;q=0.1, application/json"), Some(Format::Json));
+        assert_eq!(Format::negotiate("text/html, text/tab-separated-values;q=0.5, text/csv;q=0.5"), Some(Format::Tsv));
+        assert_eq!(Format::negotiate("text/csv;q=0, text/html"), None);
+    }
+}
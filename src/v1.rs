@@ -0,0 +1,168 @@
+//! Handlers for the `/v1` routes whose legacy responses do not follow the
+//! v1 schemas. Every other route is served unchanged under `/v1` as well.
+//!
+//! The schemas are:
+//!
+//! * a single resource is a bare object, e.g. `{"id": "2007HABE01", "name": ..}`
+//! * a collection is an object with one member named after the resource,
+//!   e.g. `{"events": [..]}`, paginated ones carry the `X-Total-Count`,
+//!   `X-Page` and `X-Per-Page` headers
+//! * times are plain numbers in the unit of the event, a missing result is `null`
+//! * errors are `{"error": {"code": .., "message": .., "details": ..}}`
+
+use std::sync::Arc;
+
+use rustc_serialize::json;
+use w::wca_data;
+
+use iron::{Handler, Request, Response, IronResult};
+use iron::status;
+use router::Router;
+
+use error::ApiError;
+use table::Format;
+use super::{CompetitorPartOfCollection, format_from_request, unknown_format, query_values, result_type_from_str,
+            page_range, set_pagination_headers, records_table, rankings_table, encode_collection};
+
+/// `{"id", "name", "gender", "country", "competition_count"}`
+pub struct CompetitorHandler {
+    pub data: Arc<wca_data::WCA>,
+}
+
+/// `{"records": [{"puzzle_id", "single", "average"}]}` in the order of `/events`
+pub struct CompetitorRecordsHandler {
+    pub data: Arc<wca_data::WCA>,
+}
+
+/// `{"rankings": [{"rank", "time", "competitor"}]}`, paginated
+pub struct RankingsHandler {
+    pub data: Arc<wca_data::WCA>,
+}
+
+/// `{"records": [{"competitor_id", "single", "average"}]}` for the given `?ids=`
+pub struct SelectiveRecordsHandler {
+    pub data: Arc<wca_data::WCA>,
+}
+
+/// `{"events": [{"id", "name"}]}`
+pub struct EventsHandler {
+    pub data: Arc<wca_data::WCA>,
+}
+
+#[derive(RustcEncodable)]
+struct EventRecord<'a> {
+    puzzle_id: &'a str,
+    single: u32,
+    average: Option<u32>,
+}
+
+#[derive(RustcEncodable)]
+struct CompetitorRecord<'a> {
+    competitor_id: &'a str,
+    single: u32,
+    average: Option<u32>,
+}
+
+#[derive(RustcEncodable)]
+struct Ranking<'a> {
+    rank: u32,
+    time: u32,
+    competitor: CompetitorPartOfCollection<'a>,
+}
+
+impl Handler for CompetitorHandler {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let ref id = req.extensions.get::<Router>().unwrap().find("id").unwrap();
+        match self.data.find_competitor(&id.to_string()) {
+            Some(competitor) => {
+                Ok(Response::with((status::Ok, json::encode(&CompetitorPartOfCollection::from_competitor(competitor)).unwrap())))
+            },
+            None => {
+                Ok(Response::with(ApiError::not_found("competitor")))
+            },
+        }
+    }
+}
+
+impl Handler for CompetitorRecordsHandler {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let ref id = req.extensions.get::<Router>().unwrap().find("id").unwrap();
+        if self.data.find_competitor(&id.to_string()).is_none() {
+            return Ok(Response::with(ApiError::not_found("competitor")));
+        }
+
+        let records: Vec<EventRecord> = match self.data.find_records(&id.to_string()) {
+            Some(records) => self.data.find_events().iter().filter_map(|e| records.get(&e.id).map(|r| EventRecord {
+                puzzle_id: e.id.as_slice(),
+                single: r.single.time,
+                average: r.average.map(|a| a.time),
+            })).collect(),
+            None => vec![],
+        };
+        Ok(Response::with((status::Ok, encode_collection(&records, "records", true))))
+    }
+}
+
+// CSV is not paginated, spreadsheets want the whole list.
+impl Handler for RankingsHandler {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let format = match format_from_request(req) {
+            Some(format) => format,
+            None         => { return Ok(unknown_format()); },
+        };
+        let puzzle_id = req.extensions.get::<Router>().unwrap().find("puzzle_id").unwrap().to_string();
+        let result_type = match result_type_from_str(req.extensions.get::<Router>().unwrap().find("type").unwrap()) {
+            Some(t) => t,
+            None    => { return Ok(Response::with(ApiError::not_found("result type"))); },
+        };
+        let rankings = match self.data.find_rankings(&puzzle_id, result_type) {
+            Some(rankings) => rankings,
+            None           => { return Ok(Response::with(ApiError::not_found("rankings"))); },
+        };
+
+        if format != Format::Json {
+            return Ok(rankings_table(&self.data, rankings).to_response(format));
+        }
+
+        let (page, per_page, start, end) = page_range(req, rankings.len());
+        let page_of_rankings: Vec<Ranking> = rankings[start..end].iter().map(|r| Ranking {
+            rank: r.rank,
+            time: r.result.time,
+            competitor: CompetitorPartOfCollection::from_competitor(self.data.find_competitor(&r.competitor_id).unwrap()),
+        }).collect();
+        let mut response = Response::with((status::Ok, encode_collection(&page_of_rankings, "rankings", true)));
+        set_pagination_headers(&mut response, page, per_page, rankings.len());
+        Ok(response)
+    }
+}
+
+impl Handler for SelectiveRecordsHandler {
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        let ids = query_values(req, "ids");
+        if ids.is_empty() {
+            return Ok(Response::with(ApiError::missing_parameter("ids")));
+        }
+        let format = match format_from_request(req) {
+            Some(format) => format,
+            None         => { return Ok(unknown_format()); },
+        };
+        let puzzle_id = req.extensions.get::<Router>().unwrap().find("puzzle_id").unwrap().to_string();
+
+        let records = self.data.find_rankings_for(&puzzle_id, ids);
+        if format != Format::Json {
+            return Ok(records_table(&records).to_response(format));
+        }
+        let records: Vec<CompetitorRecord> = records.iter().map(|r| CompetitorRecord {
+            competitor_id: r.competitor_id.as_slice(),
+            single: r.single.time,
+            average: r.average.map(|a| a.time),
+        }).collect();
+        Ok(Response::with((status::Ok, encode_collection(&records, "records", true))))
+    }
+}
+
+impl Handler for EventsHandler {
+    fn handle(&self, _: &mut Request) -> IronResult<Response> {
+        Ok(Response::with((status::Ok, encode_collection(self.data.find_events(), "events", true))))
+    }
+}
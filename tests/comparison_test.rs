@@ -0,0 +1,52 @@
+extern crate "wca-data" as w;
+
+use w::wca_data;
+use w::wca_data::{WCA, ExportFiles};
+use std::path::Path;
+
+fn setup_data() -> Box<WCA> {
+    wca_data::build_from_files(&ExportFiles::in_dir(Path::new("./tests/fixtures")))
+}
+
+#[test]
+fn compare_events() {
+    let w = setup_data();
+    let comparison = w.compare(&"2003BRUC01".to_string(), &"2005AKKE01".to_string()).unwrap();
+
+    assert_eq!(comparison.events.len(), 17);
+    assert_eq!(comparison.first_score, 0);
+    assert_eq!(comparison.second_score, 31);
+
+    let three_by_three = comparison.events.get(0).unwrap();
+    assert_eq!(three_by_three.puzzle_id, "333".to_string());
+    assert_eq!(three_by_three.single.leader, Some("2005AKKE01".to_string()));
+    assert_eq!(three_by_three.single.margin, Some(163));
+    assert_eq!(three_by_three.average.margin, Some(331));
+}
+
+#[test]
+fn compare_event_only_one_competed_in() {
+    let w = setup_data();
+    let comparison = w.compare(&"2003BRUC01".to_string(), &"2005AKKE01".to_string()).unwrap();
+
+    let multi_blind = comparison.events.iter().find(|e| e.puzzle_id == "333mbf".to_string()).unwrap();
+    assert!(multi_blind.single.first.is_none());
+    assert_eq!(multi_blind.single.leader, Some("2005AKKE01".to_string()));
+    assert!(multi_blind.single.margin.is_none());
+    assert!(multi_blind.average.leader.is_none());
+}
+
+#[test]
+fn compare_shared_competitions() {
+    let w = setup_data();
+    let comparison = w.compare(&"1982FRID01".to_string(), &"1982RAZO01".to_string()).unwrap();
+
+    assert_eq!(comparison.shared_competitions, vec!["WC1982".to_string()]);
+    assert!(comparison.events.is_empty());
+}
+
+#[test]
+fn compare_unknown_competitor() {
+    let w = setup_data();
+    assert!(w.compare(&"2003BRUC01".to_string(), &"1900NOPE01".to_string()).is_none());
+}
@@ -0,0 +1,91 @@
+extern crate "wca-data" as w;
+
+use w::wca_data;
+use w::wca_data::{WCA, ExportFiles};
+use std::path::Path;
+
+fn setup_data() -> Box<WCA> {
+    wca_data::build_from_files(&ExportFiles::in_dir(Path::new("./tests/fixtures")))
+}
+
+#[test]
+fn competition_results_grouped_by_event() {
+    let w = setup_data();
+    let events = w.find_competition_results(&"Euro2004".to_string()).unwrap();
+
+    let puzzle_ids: Vec<&str> = events.iter().map(|e| e.puzzle_id.as_slice()).collect();
+    assert_eq!(puzzle_ids, vec!["333", "444", "555", "333bf", "333oh", "333fm", "333ft", "clock", "444bf", "555bf", "magic"]);
+}
+
+#[test]
+fn competition_results_grouped_by_round() {
+    let w = setup_data();
+    let events = w.find_competition_results(&"Euro2004".to_string()).unwrap();
+    let three_by_three = events.get(0).unwrap();
+
+    let round_ids: Vec<&str> = three_by_three.rounds.iter().map(|r| r.round_id.as_slice()).collect();
+    assert_eq!(round_ids, vec!["1", "2", "f"]);
+
+    let final_round = three_by_three.rounds.last().unwrap();
+    assert_eq!(final_round.results.len(), 8);
+    let positions: Vec<u32> = final_round.results.iter().map(|r| r.pos).collect();
+    assert_eq!(positions, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+
+    let winner = final_round.results.get(0).unwrap();
+    assert_eq!(winner.competitor_id, "2003VAND01".to_string());
+    assert_eq!(winner.values, vec![1701, 1563, 1925, 1593, 1495]);
+    assert_eq!(winner.regional_average_record, Some("ER".to_string()));
+}
+
+#[test]
+fn competition_results_of_unknown_competition() {
+    let w = setup_data();
+    assert!(w.find_competition_results(&"Nope2004".to_string()).is_none());
+}
+
+#[test]
+fn competitions_of_competitor() {
+    let w = setup_data();
+    let competitions = w.find_competitions_of(&"2003BRUC01".to_string()).unwrap();
+
+    let ids: Vec<&str> = competitions.iter().map(|c| c.competition_id.as_slice()).collect();
+    assert_eq!(ids, vec!["WC2003", "DutchOpen2003", "GermanOpen2004", "Euro2004", "DutchOpen2004"]);
+    assert_eq!(competitions.get(0).unwrap().competition.unwrap().country, "Canada".to_string());
+
+    let events: Vec<&str> = competitions.get(0).unwrap().puzzle_ids.iter().map(|e| e.as_slice()).collect();
+    assert_eq!(events, vec!["333", "444", "555", "minx", "sq1", "clock", "magic"]);
+}
+
+#[test]
+fn competitions_of_competitor_without_results() {
+    let w = setup_data();
+    assert!(w.find_competitions_of(&"2011RAHM01".to_string()).unwrap().is_empty());
+    assert!(w.find_competitions_of(&"1900NOPE01".to_string()).is_none());
+}
+
+#[test]
+fn competitors_at_competition() {
+    let w = setup_data();
+    let entries = w.find_competitors_at(&"WC1982".to_string()).unwrap();
+
+    assert_eq!(entries.len(), 19);
+    assert_eq!(*entries.get(0).unwrap().competitor_id, "1982BORS01".to_string());
+    assert_eq!(*entries.last().unwrap().competitor_id, "1982VALD01".to_string());
+    assert_eq!(entries.get(0).unwrap().puzzle_ids, vec![&"333".to_string()]);
+}
+
+#[test]
+fn events_entered_at_competition() {
+    let w = setup_data();
+    let entries = w.find_competitors_at(&"GermanOpen2004".to_string()).unwrap();
+    let bruchem = entries.iter().find(|e| *e.competitor_id == "2003BRUC01".to_string()).unwrap();
+
+    let events: Vec<&str> = bruchem.puzzle_ids.iter().map(|e| e.as_slice()).collect();
+    assert_eq!(events, vec!["333", "444", "555", "333bf", "minx", "clock", "magic"]);
+}
+
+#[test]
+fn competitors_at_unknown_competition() {
+    let w = setup_data();
+    assert!(w.find_competitors_at(&"Nope2004".to_string()).is_none());
+}
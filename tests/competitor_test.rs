@@ -1,11 +1,11 @@
 extern crate "wca-data" as w;
 
 use w::wca_data;
-use w::wca_data::WCA;
+use w::wca_data::{WCA, ExportFiles};
 use std::path::Path;
 
 fn setup_data() -> Box<WCA> {
-    wca_data::build_from_files(Path::new("./tests/fixtures/persons.tsv"), Path::new("./tests/fixtures/results.tsv"), Path::new("./tests/fixtures/ranks-single.tsv"), Path::new("./tests/fixtures/ranks-average.tsv"), Path::new("./tests/fixtures/events.tsv"))
+    wca_data::build_from_files(&ExportFiles::in_dir(Path::new("./tests/fixtures")))
 }
 
 
@@ -48,3 +48,14 @@ fn test_find_competitors_several() {
     assert_eq!(competitors.iter().find(|c| c.name == "Luc Van Laethem".to_string()).is_some(), true);
     assert_eq!(competitors.iter().find(|c| c.name == "Zoltán Lábas".to_string()).is_some(), true);
 }
+
+#[test]
+fn find_competitors_by_ids_keeps_order() {
+    let w = setup_data();
+    let ids = vec!["2014RODR25".to_string(), "1900NONE01".to_string(), "1982FRID01".to_string()];
+    let competitors = w.find_competitors_by_ids(&ids);
+    assert_eq!(competitors.len(), 3);
+    assert_eq!(competitors[0].map(|c| c.id.as_slice()), Some("2014RODR25"));
+    assert!(competitors[1].is_none());
+    assert_eq!(competitors[2].map(|c| c.id.as_slice()), Some("1982FRID01"));
+}
@@ -0,0 +1,58 @@
+extern crate "wca-data" as w;
+
+use w::wca_data;
+use w::wca_data::{WCA, ExportFiles};
+use std::path::Path;
+
+fn setup_data() -> Box<WCA> {
+    wca_data::build_from_files(&ExportFiles::in_dir(Path::new("./tests/fixtures")))
+}
+
+#[test]
+fn co_competitors() {
+    let w = setup_data();
+    let co_competitors = w.find_co_competitors(&"2003BRUC01".to_string()).unwrap();
+
+    assert_eq!(co_competitors.len(), 131);
+    let most_frequent: Vec<(&str, u32)> = co_competitors.iter().take(3).map(|&(id, count)| (id.as_slice(), count)).collect();
+    assert_eq!(most_frequent, vec![("2003DENN01", 5), ("2003JANS01", 5), ("2003VAND01", 5)]);
+    assert!(co_competitors.iter().all(|&(id, _)| *id != "2003BRUC01".to_string()));
+}
+
+#[test]
+fn direct_connection() {
+    let w = setup_data();
+    let links = w.find_connection(&"1982FRID01".to_string(), &"1982RAZO01".to_string()).unwrap();
+
+    assert_eq!(links.len(), 1);
+    assert_eq!(*links.get(0).unwrap().competition_id, "WC1982".to_string());
+}
+
+#[test]
+fn connection_over_several_competitors() {
+    let w = setup_data();
+    let links = w.find_connection(&"1982THAI01".to_string(), &"2003BRUC01".to_string()).unwrap();
+
+    assert_eq!(links.len(), 2);
+    let first = links.get(0).unwrap();
+    let second = links.get(1).unwrap();
+    assert_eq!(*first.from, "1982THAI01".to_string());
+    assert_eq!(*first.competition_id, "WC1982".to_string());
+    assert_eq!(first.to, second.from);
+    assert_eq!(*second.competition_id, "WC2003".to_string());
+    assert_eq!(*second.to, "2003BRUC01".to_string());
+}
+
+#[test]
+fn connection_to_oneself() {
+    let w = setup_data();
+    assert!(w.find_connection(&"2003BRUC01".to_string(), &"2003BRUC01".to_string()).unwrap().is_empty());
+}
+
+#[test]
+fn no_connection() {
+    let w = setup_data();
+    // has not competed at any competition of the fixtures
+    assert!(w.find_connection(&"1982THAI01".to_string(), &"2005AKKE01".to_string()).is_none());
+    assert!(w.find_connection(&"1982THAI01".to_string(), &"1900NOPE01".to_string()).is_none());
+}
@@ -0,0 +1,53 @@
+extern crate "wca-data" as w;
+
+use w::wca_data;
+use w::wca_data::{WCA, ExportFiles};
+use std::path::Path;
+
+fn setup_data() -> Box<WCA> {
+    wca_data::build_from_files(&ExportFiles::in_dir(Path::new("./tests/fixtures")))
+}
+
+#[test]
+fn countries() {
+    let w = setup_data();
+    let countries = w.find_countries();
+
+    assert_eq!(countries.len(), 33);
+    assert_eq!(countries.get(0).unwrap().id, "Aruba".to_string());
+    assert_eq!(w.find_country(&"Japan".to_string()).unwrap().continent_id, "_Asia".to_string());
+}
+
+#[test]
+fn country_stats() {
+    let w = setup_data();
+    let stats = w.find_country_stats(&"USA".to_string()).unwrap();
+
+    assert_eq!(stats.competitors, 45);
+    assert_eq!((stats.genders.male, stats.genders.female, stats.genders.unknown), (41, 4, 0));
+    assert_eq!(stats.competitions, 4);
+    assert_eq!(stats.active_competitors.get(&2003), Some(&44));
+    assert_eq!(stats.events.get(&"333".to_string()), Some(&43));
+}
+
+#[test]
+fn country_stats_unknown_gender() {
+    let w = setup_data();
+    let stats = w.find_country_stats(&"Brazil".to_string()).unwrap();
+
+    assert_eq!(stats.competitors, 1);
+    assert_eq!(stats.genders.unknown, 1);
+    assert_eq!(stats.competitions, 0);
+    assert!(stats.active_competitors.is_empty());
+}
+
+#[test]
+fn country_stats_active_competitors() {
+    let w = setup_data();
+    let stats = w.find_country_stats(&"Netherlands".to_string()).unwrap();
+
+    let years: Vec<(u32, u32)> = stats.active_competitors.iter().map(|(y, c)| (*y, *c)).collect();
+    assert_eq!(years, vec![(1982, 1), (2003, 6), (2004, 6)]);
+    assert_eq!(stats.competitions, 3);
+    assert_eq!(stats.events.get(&"magic".to_string()), Some(&7));
+}
@@ -1,11 +1,11 @@
 extern crate "wca-data" as w;
 
 use w::wca_data;
-use w::wca_data::WCA;
+use w::wca_data::{WCA, ExportFiles};
 use std::path::Path;
 
 fn setup_data() -> Box<WCA> {
-    wca_data::build_from_files(Path::new("./tests/fixtures/persons.tsv"), Path::new("./tests/fixtures/results.tsv"), Path::new("./tests/fixtures/ranks-single.tsv"), Path::new("./tests/fixtures/ranks-average.tsv"), Path::new("./tests/fixtures/events.tsv"))
+    wca_data::build_from_files(&ExportFiles::in_dir(Path::new("./tests/fixtures")))
 }
 
 #[test]
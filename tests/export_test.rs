@@ -0,0 +1,32 @@
+extern crate "wca-data" as w;
+
+use w::wca_data;
+use w::wca_data::{WCA, ExportFiles};
+use std::path::Path;
+
+fn setup_data() -> Box<WCA> {
+    wca_data::build_from_files(&ExportFiles::in_dir(Path::new("./tests/fixtures")))
+}
+
+#[test]
+fn export_date_is_end_of_latest_competition() {
+    let w = setup_data();
+    // IdF2004 on 2004-12-04
+    assert_eq!(w.export_date(), Some(12756));
+}
+
+#[test]
+fn end_date_of_multi_day_competition() {
+    let w = setup_data();
+    let euro = w.find_competition(&"Euro2004".to_string()).unwrap();
+    assert_eq!(euro.end_days_since_epoch(), 12722);
+    assert_eq!(euro.end_days_since_epoch() - euro.days_since_epoch(), 1);
+}
+
+#[test]
+fn export_version_is_stable() {
+    let first = setup_data();
+    let second = setup_data();
+    assert_eq!(first.export_version().len(), 16);
+    assert_eq!(first.export_version(), second.export_version());
+}
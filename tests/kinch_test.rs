@@ -0,0 +1,46 @@
+extern crate "wca-data" as w;
+
+use w::wca_data;
+use w::wca_data::{WCA, ExportFiles, Scope};
+use std::path::Path;
+
+fn setup_data() -> Box<WCA> {
+    wca_data::build_from_files(&ExportFiles::in_dir(Path::new("./tests/fixtures")))
+}
+
+fn events(ids: Vec<&str>) -> Vec<String> {
+    ids.iter().map(|id| id.to_string()).collect()
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!((actual - expected).abs() < 0.001, "{} is not close to {}", actual, expected);
+}
+
+#[test]
+fn kinch_ranks_country() {
+    let w = setup_data();
+    let kinch = w.kinch_ranks(&events(vec!["333", "333bf", "333mbf"]), &Scope::Country("Netherlands".to_string()));
+
+    assert_eq!(kinch.len(), 2);
+    let akkersdijk = kinch.get(0).unwrap();
+    assert_eq!(akkersdijk.competitor_id, "2005AKKE01".to_string());
+    assert_eq!(akkersdijk.rank, 1);
+    assert_close(akkersdijk.score, 100.0);
+
+    let bruchem = kinch.get(1).unwrap();
+    assert_close(bruchem.events.get(0).unwrap().score, 73.7718);
+    // blindfolded uses the single when no average exists
+    assert_close(bruchem.events.get(1).unwrap().score, 24.0126);
+    assert_close(bruchem.events.get(2).unwrap().score, 0.0);
+    assert_close(bruchem.score, 32.5948);
+}
+
+#[test]
+fn kinch_ranks_multi_blind() {
+    let w = setup_data();
+    let kinch = w.kinch_ranks(&events(vec!["333mbf"]), &Scope::World);
+
+    assert_eq!(kinch.len(), 2);
+    assert_eq!(kinch.get(1).unwrap().competitor_id, "2007WEIN01".to_string());
+    assert_close(kinch.get(1).unwrap().score, 19.7562);
+}
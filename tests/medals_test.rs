@@ -0,0 +1,59 @@
+extern crate "wca-data" as w;
+
+use w::wca_data;
+use w::wca_data::{WCA, ExportFiles};
+use std::path::Path;
+
+fn setup_data() -> Box<WCA> {
+    wca_data::build_from_files(&ExportFiles::in_dir(Path::new("./tests/fixtures")))
+}
+
+#[test]
+fn competitor_medals() {
+    let w = setup_data();
+    let medals = w.find_medals(&"2003BRUC01".to_string()).unwrap();
+
+    assert_eq!((medals.total.gold, medals.total.silver, medals.total.bronze), (4, 5, 7));
+    assert_eq!(medals.total.total(), 16);
+    assert_eq!(medals.podiums.len(), 16);
+
+    let three_by_three = medals.events.get(&"333".to_string()).unwrap();
+    assert_eq!((three_by_three.gold, three_by_three.silver, three_by_three.bronze), (2, 1, 1));
+}
+
+#[test]
+fn competitor_medals_ignore_dnf_podiums() {
+    let w = setup_data();
+    let medals = w.find_medals(&"2003BRUC01".to_string()).unwrap();
+
+    assert!(medals.events.get(&"333bf".to_string()).is_none());
+    assert!(medals.podiums.iter().all(|r| r.best > 0));
+}
+
+#[test]
+fn competitor_without_medals() {
+    let w = setup_data();
+    let medals = w.find_medals(&"2011RAHM01".to_string()).unwrap();
+    assert_eq!(medals.total.total(), 0);
+    assert!(w.find_medals(&"1900NOPE01".to_string()).is_none());
+}
+
+#[test]
+fn medal_table() {
+    let w = setup_data();
+    let table = w.medal_table();
+
+    assert_eq!(table.len(), 15);
+    let countries: Vec<&str> = table.iter().take(3).map(|&(c, _)| c.as_slice()).collect();
+    assert_eq!(countries, vec!["Japan", "Netherlands", "USA"]);
+    assert_eq!(table.get(1).unwrap().1.bronze, 12);
+}
+
+#[test]
+fn medal_table_of_country() {
+    let w = setup_data();
+    let table = w.medal_table_of_country(&"Netherlands".to_string());
+
+    let competitors: Vec<&str> = table.iter().map(|&(id, _)| id.as_slice()).collect();
+    assert_eq!(competitors, vec!["2003SCHE01", "2003BRUC01", "2004NOOR01", "2003HELT01", "1982RAZO01"]);
+}
@@ -0,0 +1,59 @@
+extern crate "wca-data" as w;
+
+use w::wca_data;
+use w::wca_data::{WCA, ExportFiles};
+use std::path::Path;
+
+fn setup_data() -> Box<WCA> {
+    wca_data::build_from_files(&ExportFiles::in_dir(Path::new("./tests/fixtures")))
+}
+
+#[test]
+fn competition_details() {
+    let w = setup_data();
+    let competition = w.find_competition(&"Euro2004".to_string()).unwrap();
+
+    assert_eq!(competition.country, "Netherlands".to_string());
+    assert_eq!(competition.date(), "2004-10-30".to_string());
+}
+
+#[test]
+fn results_in_chronological_order() {
+    let w = setup_data();
+    let results = w.find_results_of(&"2003BRUC01".to_string());
+
+    assert_eq!(results.len(), 49);
+    assert_eq!(results.get(0).unwrap().competition_id, "WC2003".to_string());
+    assert_eq!(results.last().unwrap().competition_id, "DutchOpen2004".to_string());
+
+    let rounds: Vec<&str> = results.iter().filter(|r| r.competition_id == "Euro2004".to_string() && r.puzzle_id == "333".to_string()).map(|r| r.round_id.as_slice()).collect();
+    assert_eq!(rounds, vec!["1", "2", "f"]);
+}
+
+#[test]
+fn personal_best_progression() {
+    let w = setup_data();
+    let progression = w.find_progression(&"2003BRUC01".to_string(), &"333".to_string()).unwrap();
+
+    let pbs: Vec<(&str, &str, Option<i32>, Option<i32>)> = progression.iter().map(|pb| (pb.result.competition_id.as_slice(), pb.result.round_id.as_slice(), pb.single, pb.average)).collect();
+    assert_eq!(pbs, vec![("WC2003",         "1", Some(2098), None),
+                         ("WC2003",         "2", Some(1725), None),
+                         ("WC2003",         "f", None,       Some(2292)),
+                         ("GermanOpen2004", "1", None,       Some(2001)),
+                         ("GermanOpen2004", "f", Some(1598), Some(1852)),
+                         ("Euro2004",       "2", None,       Some(1839)),
+                         ("DutchOpen2004",  "2", Some(1535), Some(1712))]);
+}
+
+#[test]
+fn progression_ignores_dnf() {
+    let w = setup_data();
+    let progression = w.find_progression(&"2003BRUC01".to_string(), &"333bf".to_string()).unwrap();
+    assert!(progression.is_empty());
+}
+
+#[test]
+fn progression_of_unknown_competitor() {
+    let w = setup_data();
+    assert!(w.find_progression(&"1900NOPE01".to_string(), &"333".to_string()).is_none());
+}
@@ -0,0 +1,60 @@
+extern crate "wca-data" as w;
+
+use w::wca_data;
+use w::wca_data::{WCA, ExportFiles, RecordLevel};
+use std::path::Path;
+
+fn setup_data() -> Box<WCA> {
+    wca_data::build_from_files(&ExportFiles::in_dir(Path::new("./tests/fixtures")))
+}
+
+#[test]
+fn world_record_history() {
+    let w = setup_data();
+    let history = w.find_record_history(&"333".to_string(), wca_data::ResultType::Single, RecordLevel::World);
+
+    let times: Vec<i32> = history.iter().map(|r| r.time).collect();
+    assert_eq!(times, vec![2295, 1671, 1653, 1507, 1476, 1393, 1211]);
+    assert_eq!(history.get(0).unwrap().result.competitor_id, "1982THAI01".to_string());
+    assert_eq!(history.get(0).unwrap().days_stood, Some(7749));
+    assert_eq!(history.get(1).unwrap().days_stood, Some(0));
+    assert!(history.last().unwrap().days_stood.is_none());
+}
+
+#[test]
+fn world_record_history_average() {
+    let w = setup_data();
+    let history = w.find_record_history(&"333".to_string(), wca_data::ResultType::Average, RecordLevel::World);
+
+    let times: Vec<i32> = history.iter().map(|r| r.time).collect();
+    assert_eq!(times, vec![2000, 1653, 1538, 1452]);
+    assert_eq!(history.last().unwrap().result.competition_id, "CaltechFall2004".to_string());
+}
+
+#[test]
+fn continental_record_history() {
+    let w = setup_data();
+    let history = w.find_record_history(&"333".to_string(), wca_data::ResultType::Single, RecordLevel::Continent);
+    let europe: Vec<(i32, Option<i64>)> = history.iter().filter(|r| r.region == "_Europe".to_string()).map(|r| (r.time, r.days_stood)).collect();
+
+    // world records count as continental records as well
+    assert_eq!(europe, vec![(2432, Some(7749)), (1830, Some(0)), (1653, Some(287)), (1373, None)]);
+}
+
+#[test]
+fn national_record_history() {
+    let w = setup_data();
+    let history = w.find_record_history(&"333".to_string(), wca_data::ResultType::Single, RecordLevel::Country);
+    let netherlands: Vec<i32> = history.iter().filter(|r| r.region == "Netherlands".to_string()).map(|r| r.time).collect();
+
+    assert_eq!(netherlands, vec![2432, 2098, 1725, 1598, 1535]);
+}
+
+#[test]
+fn national_records_across_rounds() {
+    let w = setup_data();
+    let history = w.find_record_history(&"333".to_string(), wca_data::ResultType::Single, RecordLevel::Country);
+    let china: Vec<i32> = history.iter().filter(|r| r.region == "China".to_string()).map(|r| r.time).collect();
+
+    assert_eq!(china, vec![4365, 4317, 3179, 2965, 2726, 2395, 2089, 2075]);
+}
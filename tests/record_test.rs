@@ -1,11 +1,11 @@
 extern crate "wca-data" as w;
 
 use w::wca_data;
-use w::wca_data::WCA;
+use w::wca_data::{WCA, ExportFiles};
 use std::path::Path;
 
 fn setup_data() -> Box<WCA> {
-    wca_data::build_from_files(Path::new("./tests/fixtures/persons.tsv"), Path::new("./tests/fixtures/results.tsv"), Path::new("./tests/fixtures/ranks-single.tsv"), Path::new("./tests/fixtures/ranks-average.tsv"), Path::new("./tests/fixtures/events.tsv"))
+    wca_data::build_from_files(&ExportFiles::in_dir(Path::new("./tests/fixtures")))
 }
 
 #[test]
@@ -73,3 +73,68 @@ fn records_of_multiple_competitors() {
     assert_eq!(records.get(1).unwrap().single.time, 871);
     assert_eq!(records.get(1).unwrap().competitor_id, "2003BRUC01".to_string());
 }
+
+#[test]
+fn rank_position_single() {
+    let w = setup_data();
+    let position = w.find_rank_position(&"2003BRUC01".to_string(), &"333".to_string(), wca_data::ResultType::Single).unwrap();
+
+    assert_eq!(position.ranking.rank, 2);
+    assert_eq!(position.ranking.result.time, 871);
+    assert_eq!(position.total, 4);
+    assert_eq!(position.above.unwrap().competitor_id, "2005AKKE01".to_string());
+    assert_eq!(position.below.unwrap().competitor_id, "2007WEIN01".to_string());
+}
+
+#[test]
+fn rank_position_first_and_last() {
+    let w = setup_data();
+    let first = w.find_rank_position(&"2005AKKE01".to_string(), &"333".to_string(), wca_data::ResultType::Average).unwrap();
+    assert_eq!(first.ranking.rank, 1);
+    assert_eq!(first.total, 2);
+    assert!(first.above.is_none());
+    assert_eq!(first.below.unwrap().competitor_id, "2003BRUC01".to_string());
+
+    let last = w.find_rank_position(&"2011RAHM01".to_string(), &"333".to_string(), wca_data::ResultType::Single).unwrap();
+    assert_eq!(last.ranking.rank, 4);
+    assert!(last.below.is_none());
+}
+
+#[test]
+fn rank_position_without_result() {
+    let w = setup_data();
+    assert!(w.find_rank_position(&"2011RAHM01".to_string(), &"333".to_string(), wca_data::ResultType::Average).is_none());
+    assert!(w.find_rank_position(&"1982FRID01".to_string(), &"333".to_string(), wca_data::ResultType::Single).is_none());
+}
+
+#[test]
+fn current_national_records() {
+    let w = setup_data();
+    let records = w.current_records(&wca_data::Scope::Country("Netherlands".to_string()));
+
+    assert_eq!(records.len(), 17);
+    let three_by_three = records.get(0).unwrap();
+    assert_eq!(*three_by_three.puzzle_id, "333".to_string());
+    assert_eq!(three_by_three.single.as_ref().unwrap().time, 708);
+    assert_eq!(three_by_three.single.as_ref().unwrap().holders, vec![&"2005AKKE01".to_string()]);
+    assert_eq!(three_by_three.average.as_ref().unwrap().time, 931);
+
+    let multi_blind = records.iter().find(|r| *r.puzzle_id == "333mbf".to_string()).unwrap();
+    assert!(multi_blind.average.is_none());
+}
+
+#[test]
+fn current_continental_records() {
+    let w = setup_data();
+    let records = w.current_records(&wca_data::Scope::Continent("_Asia".to_string()));
+
+    assert_eq!(records.len(), 7);
+    assert_eq!(records.get(0).unwrap().single.as_ref().unwrap().time, 4647);
+    assert!(records.get(0).unwrap().average.is_none());
+}
+
+#[test]
+fn current_records_of_country_without_competitors() {
+    let w = setup_data();
+    assert!(w.current_records(&wca_data::Scope::Country("Aruba".to_string())).is_empty());
+}
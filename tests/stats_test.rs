@@ -0,0 +1,54 @@
+extern crate "wca-data" as w;
+
+use w::wca_data;
+use w::wca_data::{WCA, ExportFiles, Scope};
+use std::path::Path;
+
+fn setup_data() -> Box<WCA> {
+    wca_data::build_from_files(&ExportFiles::in_dir(Path::new("./tests/fixtures")))
+}
+
+#[test]
+fn distribution() {
+    let w = setup_data();
+    let distribution = w.distribution(&"333".to_string(), wca_data::ResultType::Single, None, &Scope::World, 4).unwrap();
+
+    assert_eq!(distribution.count, 4);
+    assert_eq!(distribution.mean, 1822.75);
+    assert_eq!((distribution.p1, distribution.p10, distribution.p50, distribution.p90), (708, 708, 871, 4647));
+}
+
+#[test]
+fn distribution_histogram() {
+    let w = setup_data();
+    let distribution = w.distribution(&"333".to_string(), wca_data::ResultType::Single, None, &Scope::World, 4).unwrap();
+
+    let buckets: Vec<(u32, u32, usize)> = distribution.histogram.iter().map(|b| (b.from, b.to, b.count)).collect();
+    assert_eq!(buckets, vec![(708, 1693, 3), (1693, 2678, 0), (2678, 3663, 0), (3663, 4648, 1)]);
+}
+
+#[test]
+fn distribution_of_country() {
+    let w = setup_data();
+    let distribution = w.distribution(&"333".to_string(), wca_data::ResultType::Average, None, &Scope::Country("Netherlands".to_string()), 20).unwrap();
+
+    assert_eq!(distribution.count, 2);
+    assert_eq!(distribution.p50, 931);
+    assert_eq!(distribution.histogram.iter().fold(0, |sum, b| sum + b.count), 2);
+}
+
+#[test]
+fn distribution_of_gender() {
+    let w = setup_data();
+    assert_eq!(w.distribution(&"333".to_string(), wca_data::ResultType::Single, Some(wca_data::Gender::Male), &Scope::World, 20).unwrap().count, 4);
+    assert!(w.distribution(&"333".to_string(), wca_data::ResultType::Single, Some(wca_data::Gender::Female), &Scope::World, 20).is_none());
+}
+
+#[test]
+fn distribution_with_too_many_buckets() {
+    let w = setup_data();
+    assert!(w.distribution(&"333".to_string(), wca_data::ResultType::Single, None, &Scope::World, wca_data::MAX_BUCKETS).is_some());
+    assert!(w.distribution(&"333".to_string(), wca_data::ResultType::Single, None, &Scope::World, 1000000000).is_none());
+    // would truncate to 0 buckets as u32
+    assert!(w.distribution(&"333".to_string(), wca_data::ResultType::Single, None, &Scope::World, 4294967296).is_none());
+}
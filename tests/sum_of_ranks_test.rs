@@ -0,0 +1,58 @@
+extern crate "wca-data" as w;
+
+use w::wca_data;
+use w::wca_data::{WCA, ExportFiles, Scope};
+use std::path::Path;
+
+fn setup_data() -> Box<WCA> {
+    wca_data::build_from_files(&ExportFiles::in_dir(Path::new("./tests/fixtures")))
+}
+
+fn events(ids: Vec<&str>) -> Vec<String> {
+    ids.iter().map(|id| id.to_string()).collect()
+}
+
+#[test]
+fn rankings_in_country() {
+    let w = setup_data();
+    let rankings = w.find_rankings_in(&"333".to_string(), wca_data::ResultType::Single, &Scope::Country("Netherlands".to_string()));
+
+    assert_eq!(rankings.len(), 2);
+    assert_eq!(rankings.get(1).unwrap().0, 2);
+    assert_eq!(rankings.get(1).unwrap().1.competitor_id, "2003BRUC01".to_string());
+}
+
+#[test]
+fn rankings_in_continent() {
+    let w = setup_data();
+    let rankings = w.find_rankings_in(&"333".to_string(), wca_data::ResultType::Single, &Scope::Continent("_Europe".to_string()));
+
+    assert_eq!(rankings.len(), 3);
+    assert!(rankings.iter().all(|&(_, r)| r.competitor_id != "2011RAHM01".to_string()));
+}
+
+#[test]
+fn sum_of_ranks_world() {
+    let w = setup_data();
+    let sor = w.sum_of_ranks(&events(vec!["333", "444", "333mbf"]), wca_data::ResultType::Single, &Scope::World);
+
+    assert_eq!(sor.len(), 4);
+    let sums: Vec<(String, u32)> = sor.iter().map(|s| (s.competitor_id.clone(), s.sum)).collect();
+    assert_eq!(sums, vec![("2005AKKE01".to_string(), 3), ("2003BRUC01".to_string(), 7), ("2007WEIN01".to_string(), 8), ("2011RAHM01".to_string(), 11)]);
+
+    let bruchem = sor.get(1).unwrap();
+    assert_eq!(bruchem.rank, 2);
+    assert_eq!(bruchem.events.get(2).unwrap().rank, 3);
+    assert!(bruchem.events.get(2).unwrap().penalty);
+}
+
+#[test]
+fn sum_of_ranks_country() {
+    let w = setup_data();
+    let sor = w.sum_of_ranks(&events(vec!["333", "333ft"]), wca_data::ResultType::Average, &Scope::Country("Netherlands".to_string()));
+
+    assert_eq!(sor.len(), 2);
+    assert_eq!(sor.get(0).unwrap().competitor_id, "2005AKKE01".to_string());
+    assert_eq!(sor.get(0).unwrap().sum, 2);
+    assert_eq!(sor.get(1).unwrap().sum, 4);
+}